@@ -1,9 +1,18 @@
-use crate::geometry::Mesh;
-use crate::material::ShaderUnlit;
-use crate::rubik::{Rubik, Move};
-use crate::world::{new_entity, new_light, Node, NodeRef, Renderer};
+use crate::hypercube::Hypercube;
+use crate::launch::LaunchOptions;
+use crate::rubik::{
+    keymap_panel, net_panel, paint_panel, repaint_cube, AlgorithmPlayer, AttractMode, DragTurn,
+    Facelet, Keymap, Move, PuzzleConfig, Rubik, RubikNet, StickerPaint, Turn, LAYER_CENTERS,
+    LAYER_CORE, LAYER_CORNERS, LAYER_EDGES,
+};
+use crate::touch_gestures::{Gesture, TouchGestures};
+use crate::world::{
+    descendants, find_by_tag, find_path, is_shown, layout_panel, node_path, Animation, Animator,
+    Camera, CameraMode, CameraView, Easing, LoopMode, Node, NodeRef, Projection, Renderer,
+    SceneNode, Track, Variant, ViewLayout, Viewport, LAYER_ALL, raycast, world_bounds,
+};
 use egui_winit::State as EguiState;
-use glam::Vec4;
+use glam::{EulerRot, Quat, Vec2, Vec3, Vec4};
 use std::f32::consts::PI;
 use std::rc::Rc;
 use std::sync::Arc;
@@ -13,13 +22,28 @@ use std::time::Instant;
 use web_time::Instant;
 use wgpu::Color;
 use winit::application::ApplicationHandler;
-use winit::event::{ElementState, MouseButton, StartCause, WindowEvent};
+use winit::event::{ElementState, MouseButton, StartCause, TouchPhase, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy};
 use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::{Window, WindowId};
 
 const LIGHT_RADIUS: f32 = 10.0;
-const LIGHT_INTENSITY: f32 = 2.5;
+/// Scene loaded at startup, read from disk on native so it can be edited
+/// without rebuilding
+const SCENE_PATH: &str = "assets/scene.ron";
+const SCENE_DEFAULT: &str = include_str!("../assets/scene.ron");
+/// Seconds before the lights repeat their wandering
+const LIGHT_LOOP: f32 = 60.0;
+/// Seconds between the keyframes of a light
+const LIGHT_KEY_STEP: f32 = 0.5;
+/// Seconds the lights take to come on
+const LIGHT_FADE_IN: f32 = 1.5;
+/// Seconds between two clicks making a double click
+const DOUBLE_CLICK: f32 = 0.3;
+/// Render layer of the cubes marking where the lights are
+const LAYER_LIGHT_MARKERS: u32 = 1 << 8;
+/// Where the net is drawn over the cube, bottom left
+const NET_RECT: [f32; 4] = [0.01, 0.64, 0.35, 0.35];
 const WINDOW_WIDTH: u32 = 1024;
 const WINDOW_HEIGHT: u32 = 768;
 
@@ -28,26 +52,84 @@ pub struct App {
     start_time_stamp: Instant,
     last_frame_timestamp: Instant,
     renderer: Option<Renderer>,
-    lights: Vec<(NodeRef, NodeRef, u128)>,
+    animator: Animator,
     event_loop: Option<EventLoopProxy<Renderer>>,
     rubik: Rubik,
+    hypercube: Hypercube,
+    show_hypercube: bool,
+    player: AlgorithmPlayer,
+    attract: AttractMode,
+    drag: DragTurn,
+    keymap: Keymap,
+    /// Binding waiting for a key press to be assigned
+    rebinding: Option<usize>,
+    shift: bool,
+    touches: TouchGestures,
+    /// Rotation of the whole cube by twisting two fingers, on top of its spin
+    cube_twist: Quat,
+    layout: ViewLayout,
+    net: RubikNet,
+    /// Draw the net over the cube as well as in its window
+    net_overlay: bool,
+    /// Colours being painted after a real cube, the cube can't turn meanwhile
+    paint: Option<StickerPaint>,
+    /// What came of the last painting action
+    paint_status: Option<String>,
+    algorithm_text: String,
+    algorithm_error: Option<String>,
+    seed_text: String,
+    /// Scene file asked for at launch
+    scene_path: Option<String>,
+    /// Path or `#tag` of the nodes shown in the scene panel
+    node_query: String,
     egui_state: Option<EguiState>,
     egui_ctx: egui::Context,
     mouse_down: bool,
+    /// Right button held, dragging pans the camera
+    panning: bool,
+    /// When the background was last clicked, to catch double clicks
+    last_click: Option<Instant>,
     last_mouse_pos: (f32, f32),
     egui_frame_started: bool,
 }
 
 impl App {
-    pub fn new(event_loop: &EventLoop<Renderer>) -> Self {
+    /// A seed in `options` replays the random moves of an earlier run, a
+    /// fresh one is picked otherwise
+    pub fn new(event_loop: &EventLoop<Renderer>, options: LaunchOptions) -> Self {
+        let mut rubik = Rubik::new();
+        if let Some(seed) = options.seed {
+            rubik.reseed(seed);
+        }
+        log::info!("random seed {}", rubik.seed());
         Self {
             window: None,
             start_time_stamp: Instant::now(),
             last_frame_timestamp: Instant::now(),
             renderer: None,
-            lights: Vec::new(),
+            animator: Animator::new(),
             event_loop: Some(event_loop.create_proxy()),
-            rubik: Rubik::new(),
+            seed_text: rubik.seed().to_string(),
+            scene_path: options.scene,
+            node_query: String::from("#light"),
+            rubik,
+            hypercube: Hypercube::new(),
+            show_hypercube: false,
+            player: AlgorithmPlayer::new(),
+            attract: AttractMode::new(),
+            drag: DragTurn::new(),
+            keymap: Keymap::load(),
+            rebinding: None,
+            shift: false,
+            touches: TouchGestures::new(),
+            cube_twist: Quat::IDENTITY,
+            layout: ViewLayout::Single,
+            net: RubikNet::new(),
+            net_overlay: false,
+            paint: None,
+            paint_status: None,
+            algorithm_text: String::from("R U R' U'"),
+            algorithm_error: None,
             egui_state: None,
             egui_ctx: {
                 let ctx = egui::Context::default();
@@ -58,12 +140,120 @@ impl App {
                 ctx
             },
             mouse_down: false,
+            panning: false,
+            last_click: None,
             last_mouse_pos: (0.0, 0.0),
             egui_frame_started: false,
         }
     }
 }
 
+/// The facelet of the sticker of `rubik` under the cursor at `x`, `y`
+fn facelet_at(rubik: &Rubik, renderer: &Renderer, x: f32, y: f32) -> Option<Facelet> {
+    let ray = renderer.ray(x, y);
+    let (index, _, normal) = rubik.sticker_hit(&ray, renderer.camera.layers)?;
+    Facelet::from_sticker(rubik.state.cubies[index].position, normal, rubik.state.span)
+}
+
+/// Index of the viewport drawing `net` over the cube, if it is on
+fn net_viewport(renderer: &Renderer, net: &RubikNet) -> Option<usize> {
+    renderer.viewports.iter().position(|viewport| {
+        viewport
+            .scene
+            .as_ref()
+            .is_some_and(|scene| Rc::ptr_eq(scene, &net.root))
+    })
+}
+
+/// Read the startup scene, from `path` or `SCENE_PATH` on native, falling
+/// back to the copy built into the app. `.json` files are read as JSON.
+/// A scene asked for by `path` that can't be read is logged as an error.
+fn load_scene(path: Option<&str>) -> Result<SceneNode, String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let explicit = path.is_some();
+        let path = path.unwrap_or(SCENE_PATH);
+        let scene = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| {
+                if path.ends_with(".json") {
+                    SceneNode::from_json(&text)
+                } else {
+                    SceneNode::from_ron(&text)
+                }
+            });
+        match scene {
+            Ok(scene) => return Ok(scene),
+            // the default file is only there when running from the source tree
+            Err(_) if !explicit && !std::path::Path::new(path).exists() => {}
+            Err(e) => log::error!("failed to load scene {path}: {e}, using the built-in one"),
+        }
+    }
+    // there are no scene files to read on the web
+    #[cfg(target_arch = "wasm32")]
+    let _ = path;
+    SceneNode::from_ron(SCENE_DEFAULT)
+}
+
+/// Save a snapshot of the scene under `root`, to a file next to the app on
+/// native and to the log on the web
+fn export_scene(root: &NodeRef, format: &str) {
+    let scene = SceneNode::from_node(root);
+    let text = if format == "json" { scene.to_json() } else { scene.to_ron() };
+    let text = match text {
+        Ok(text) => text,
+        Err(e) => {
+            log::error!("failed to export scene: {e}");
+            return;
+        }
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path = format!("scene-export.{format}");
+        match std::fs::write(&path, text) {
+            Ok(()) => log::info!("exported scene to {path}"),
+            Err(e) => log::error!("failed to write {path}: {e}"),
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    log::info!("exported scene:\n{text}");
+}
+
+/// Key the wandering of a light around the cube and the spin of its marker,
+/// looping every `LIGHT_LOOP` seconds. `time_offset` in milliseconds shifts
+/// the light along its path.
+fn light_animations(light: NodeRef, marker: NodeRef, time_offset: f32) -> (Animation, Animation) {
+    // every wave goes round a whole number of times in a loop, so the end of
+    // the loop meets its start
+    let wave = |cycles: f32, time: f32| (PI * 2.0 * cycles * time / LIGHT_LOOP).sin();
+    let pose = |key_time: f32| {
+        let time = key_time + time_offset / 1000.0;
+        let rx = PI * 2.0 * wave(4.0, time);
+        let ry = PI * 2.0 * wave(1.0, time);
+        let rz = PI * 2.0 * wave(3.0, time);
+        let rotation = Quat::from_euler(EulerRot::XYZ, rx, ry, rz);
+        let x = 4.0 * wave(5.0, time);
+        let y = 4.0 * wave(7.0, time);
+        let z = 4.0 * wave(14.0, time);
+        let position = Vec4::new(x, y, z, 1.0).normalize().truncate() * LIGHT_RADIUS;
+        (position, rotation)
+    };
+    let (position, rotation) = pose(0.0);
+    let mut path = Track::new(position);
+    let mut spin = Track::new(rotation);
+    let steps = (LIGHT_LOOP / LIGHT_KEY_STEP) as u32;
+    for step in 1..=steps {
+        let key_time = step as f32 * LIGHT_KEY_STEP;
+        let (position, rotation) = pose(key_time);
+        path = path.key(key_time, position, Easing::Linear);
+        spin = spin.key(key_time, rotation, Easing::Linear);
+    }
+    (
+        Animation::new(light, LoopMode::Repeat).with_translation(path),
+        Animation::new(marker, LoopMode::Repeat).with_rotation(spin),
+    )
+}
+
 impl App {
     pub async fn make_renderer(window: Arc<Window>) -> Renderer {
         Renderer::new(window.clone(), WINDOW_WIDTH, WINDOW_HEIGHT).await
@@ -73,114 +263,290 @@ impl App {
             return;
         };
         let app_init_timestamp = Instant::now();
-        let cube_mesh = Rc::new(Mesh::new_cube(0xcba6f7ff, &renderer.device));
-        let shader_unlit = Rc::new(ShaderUnlit::new(&renderer));
-        self.rubik.generate_pieces(1, &renderer);
+        self.rubik.generate_pieces(PuzzleConfig::new(1), renderer);
+        renderer.camera.frame(self.rubik.radius(), renderer.aspect_ratio());
         self.rubik.start_move_random();
         renderer.root.add_child(self.rubik.root.clone());
-        let lights = vec![
-            (
-                Color {
-                    r: 1.0,
-                    g: 0.8,
-                    b: 1.0,
-                    a: 1.0,
-                },
-                LIGHT_RADIUS,
-                LIGHT_INTENSITY,
-                0,
-            ),
-            (
-                Color {
-                    r: 0.0,
-                    g: 0.5,
-                    b: 1.0,
-                    a: 1.0,
-                },
-                LIGHT_RADIUS,
-                LIGHT_INTENSITY,
-                2200,
-            ),
-            (
-                Color {
-                    r: 0.8,
-                    g: 0.5,
-                    b: 1.0,
-                    a: 1.0,
-                },
-                LIGHT_RADIUS,
-                LIGHT_INTENSITY,
-                6200,
-            ),
-            (
-                Color {
-                    r: 1.0,
-                    g: 1.0,
-                    b: 0.5,
-                    a: 1.0,
-                },
-                LIGHT_RADIUS,
-                LIGHT_INTENSITY,
-                4400,
-            ),
-        ];
-        self.lights = lights
-            .into_iter()
-            .map(|(color, radius, intensity, time_offset)| {
-                let mut cube = new_entity(cube_mesh.clone(), shader_unlit.clone());
-                cube.scale_uniform(0.7);
-                cube.translate(1.0, 1.0, 1.0);
-                let mut light = new_light(color, radius * intensity);
-                light.add_child(cube.clone());
-                renderer.root.add_child(light.clone());
-                (light, cube, time_offset)
-            })
-            .collect();
+        self.hypercube.generate_pieces(renderer);
+        self.hypercube.start_move_random();
+        let scene = load_scene(self.scene_path.as_deref()).and_then(|scene| scene.build(renderer));
+        match scene {
+            Ok(scene) => renderer.root.add_child(scene),
+            Err(e) => log::error!("failed to load scene: {e}"),
+        }
+        let lights: Vec<NodeRef> = find_by_tag(&renderer.root, "light").collect();
+        for light in lights {
+            let (color, strength, time_offset) = {
+                let data = light.borrow();
+                let Variant::Light(color, radius) = data.variant else {
+                    continue;
+                };
+                let time_offset = data.extras.get("time_offset").copied().unwrap_or(0.0);
+                (color, radius, time_offset)
+            };
+            let Some(mut marker) = find_path(&light, "marker") else {
+                continue;
+            };
+            marker.set_layers(LAYER_LIGHT_MARKERS);
+            let (wander, spin) = light_animations(light.clone(), marker.clone(), time_offset);
+            self.animator.add(wander);
+            self.animator.add(spin);
+            let phase = time_offset / 1000.0;
+            self.animator.add(
+                Animation::new(light.clone(), LoopMode::Once)
+                    .with_color(Track::tween(Color::BLACK, color, LIGHT_FADE_IN, Easing::QuadOut)),
+            );
+            let mut glow = Animation::new(light, LoopMode::PingPong).with_radius(Track::tween(
+                strength * 0.85,
+                strength * 1.15,
+                3.0,
+                Easing::SineInOut,
+            ));
+            glow.seek(phase);
+            self.animator.add(glow);
+            let mut pulse = Animation::new(marker, LoopMode::PingPong).with_scale(Track::tween(
+                Vec3::splat(0.6),
+                Vec3::splat(0.8),
+                1.5,
+                Easing::SineInOut,
+            ));
+            pulse.seek(phase);
+            self.animator.add(pulse);
+        }
         println!("app initialized in {:?}", app_init_timestamp.elapsed());
     }
     pub fn update(&mut self, delta_time: f32, time: u128) {
-        for (light, cube, time_offset) in self.lights.iter_mut() {
-            let time = time + *time_offset;
-            let rx = PI * 2.0 * (0.00042 * time as f64).sin() as f32;
-            let ry = PI * 2.0 * (0.00011 * time as f64).sin() as f32;
-            let rz = PI * 2.0 * (0.00027 * time as f64).sin() as f32;
-            cube.rotate(rx, ry, rz);
-            let x = 4.0 * (0.00058 * time as f64).sin() as f32;
-            let y = 4.0 * (0.00076 * time as f64).sin() as f32;
-            let z = 4.0 * (0.00142 * time as f64).sin() as f32;
-            let v = Vec4::new(x, y, z, 1.0).normalize() * LIGHT_RADIUS;
-            light.translate(v.x, v.y, v.z);
+        self.animator.update(delta_time);
+        if self.show_hypercube {
+            self.hypercube.update(delta_time);
+            self.hypercube.root.rotate_z((0.0003 * time as f64) as f32);
+        } else {
+            if self.player.is_loaded() {
+                self.player.update(delta_time, &mut self.rubik);
+            } else if let Some(renderer) = self.renderer.as_mut() {
+                // painting counts as using the cube
+                if self.paint.is_some() {
+                    self.attract.input(&mut self.rubik);
+                }
+                self.attract
+                    .update(delta_time, &mut self.rubik, &mut renderer.camera);
+                // a layer held by the mouse stays where the mouse puts it
+                if !self.attract.is_active() && !self.drag.is_active() {
+                    self.rubik.update(delta_time);
+                }
+            }
+            let spin = Quat::from_rotation_z((0.0003 * time as f64) as f32);
+            self.rubik.root.rotate_quat(self.cube_twist * spin);
         }
-        self.rubik.update(delta_time);
-        self.rubik.root.rotate_z((0.0003 * time as f64) as f32);
         let Some(renderer) = self.renderer.as_mut() else {
             return;
         };
         renderer.time = time as f32;
+        renderer.camera.update(delta_time);
+        self.layout.update(renderer);
+        match (self.net_overlay, net_viewport(renderer, &self.net)) {
+            (true, None) => renderer.viewports.push(Viewport {
+                camera: Camera::new(),
+                rect: NET_RECT,
+                scene: Some(self.net.root.clone()),
+            }),
+            (false, Some(i)) => {
+                renderer.viewports.remove(i);
+            }
+            _ => {}
+        }
+        if self.net_overlay {
+            // recoloured every frame, so it follows the cube after every move
+            let (rubik, paint) = (&self.rubik, &self.paint);
+            self.net.sync(rubik, renderer, |facelet| match paint {
+                Some(paint) => Some(paint.color(facelet)),
+                None => facelet.color(rubik),
+            });
+            let aspect_ratio = (NET_RECT[2] * renderer.config.width as f32)
+                / (NET_RECT[3] * renderer.config.height as f32).max(1.0);
+            if let Some(viewport) = renderer.viewports.last_mut() {
+                self.net.fit_camera(&mut viewport.camera, aspect_ratio);
+            }
+        }
 
         // Update egui
-        if let Some(egui_state) = self.egui_state.as_mut() {
-            if let Some(window) = self.window.as_ref() {
-                let raw_input = egui_state.take_egui_input(window);
-                self.egui_ctx.begin_pass(raw_input);
-                self.egui_frame_started = true;
+        if let Some(egui_state) = self.egui_state.as_mut()
+            && let Some(window) = self.window.as_ref()
+        {
+            let raw_input = egui_state.take_egui_input(window);
+            self.egui_ctx.begin_pass(raw_input);
+            self.egui_frame_started = true;
 
-                // Create debug GUI
-                egui::Window::new("Debug Controls")
-                    .show(&self.egui_ctx, |ui| {
-                        ui.heading("Rubik's Cube Controls");
+            // Create debug GUI
+            egui::Window::new("Debug Controls")
+                .show(&self.egui_ctx, |ui| {
+                    ui.heading("Rubik's Cube Controls");
 
-                        ui.separator();
+                    ui.separator();
+                    if self.paint.is_some() {
+                        ui.label("Finish painting to change the cube");
+                        ui.disable();
+                    }
+
+                    if ui.checkbox(&mut self.show_hypercube, "4D Hypercube").changed() {
+                        let (shown, hidden) = if self.show_hypercube {
+                            (&self.hypercube.root, &self.rubik.root)
+                        } else {
+                            (&self.rubik.root, &self.hypercube.root)
+                        };
+                        renderer.root.extract_child_if(|node| Rc::ptr_eq(node, hidden));
+                        renderer.root.add_child(shown.clone());
+                    }
+
+                    if ui.button(if self.rubik.paused { "Resume" } else { "Pause" }).clicked() {
+                        self.rubik.paused = !self.rubik.paused;
+                    }
+
+                    ui.checkbox(&mut self.rubik.auto_move, "Auto Move");
 
-                        if ui.button(if self.rubik.paused { "Resume" } else { "Pause" }).clicked() {
-                            self.rubik.paused = !self.rubik.paused;
+                    ui.horizontal(|ui| {
+                        ui.label("Seed");
+                        ui.text_edit_singleline(&mut self.seed_text);
+                        if ui.button("Restart").clicked() {
+                            match self.seed_text.trim().parse() {
+                                Ok(seed) => {
+                                    self.player.unload(&mut self.rubik);
+                                    self.rubik.generate_pieces(self.rubik.config.clone(), renderer);
+                                    renderer.camera.frame(self.rubik.radius(), renderer.aspect_ratio());
+                                    self.rubik.reseed(seed);
+                                    if self.rubik.auto_move {
+                                        self.rubik.start_move_random();
+                                    }
+                                }
+                                Err(_) => self.seed_text = self.rubik.seed().to_string(),
+                            }
                         }
+                    });
 
-                        ui.checkbox(&mut self.rubik.auto_move, "Auto Move");
+                    let mut void_cube = self.rubik.config.remove_centers;
+                    if ui.checkbox(&mut void_cube, "Void Cube").changed() {
+                        let mut config = self.rubik.config.clone();
+                        config.remove_centers = void_cube;
+                        self.rubik.generate_pieces(config, renderer);
+                        renderer.camera.frame(self.rubik.radius(), renderer.aspect_ratio());
+                    }
+                    let mut mirror = self.rubik.config.sticker_color.is_some();
+                    if ui.checkbox(&mut mirror, "Mirror Blocks").changed() {
+                        let span = self.rubik.config.span;
+                        let shape = if mirror {
+                            PuzzleConfig::mirror(span)
+                        } else {
+                            PuzzleConfig::new(span)
+                        };
+                        let config = PuzzleConfig {
+                            layer_thickness: shape.layer_thickness,
+                            sticker_color: shape.sticker_color,
+                            ..self.rubik.config.clone()
+                        };
+                        self.rubik.generate_pieces(config, renderer);
+                        renderer.camera.frame(self.rubik.radius(), renderer.aspect_ratio());
+                    }
+                    if self.rubik.state.is_solved() {
+                        ui.label("Solved");
+                    } else if self.rubik.config.remove_centers && self.rubik.state.has_parity() {
+                        // with centers to see, the parity is just a slice or
+                        // rotation that can be turned back
+                        ui.label("Parity");
+                    }
+
+                    ui.separator();
+                    ui.label("Animation:");
+                    let animation = &mut self.rubik.animation;
+                    egui::ComboBox::from_label("Easing")
+                        .selected_text(animation.easing.map_or("Random".into(), |e| e.name()))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut animation.easing, None, "Random");
+                            for easing in Easing::ALL {
+                                ui.selectable_value(&mut animation.easing, Some(easing), easing.name());
+                            }
+                        });
+                    ui.add(egui::Slider::new(&mut animation.duration, 0.1..=3.0).text("Duration"));
+                    ui.checkbox(&mut animation.scale_by_turns, "Scale by turn amount");
+
+                    ui.separator();
+                    ui.label("Attract Mode:");
+                    ui.horizontal(|ui| {
+                        if self.attract.is_active() {
+                            if ui.button("Stop").clicked() {
+                                self.attract.stop(&mut self.rubik);
+                            }
+                        } else if ui
+                            .add_enabled(!self.player.is_loaded(), egui::Button::new("Start"))
+                            .clicked()
+                        {
+                            self.attract.start(&mut self.rubik, &mut renderer.camera);
+                        }
+                        if let Some(phase) = self.attract.phase() {
+                            ui.label(format!("{phase:?}"));
+                        }
+                    });
+                    let attract = &mut self.attract.settings;
+                    ui.checkbox(&mut attract.auto_start, "Start when idle");
+                    ui.add(egui::Slider::new(&mut attract.idle_time, 5.0..=600.0).text("Idle seconds"));
+                    ui.add(egui::Slider::new(&mut attract.scramble_length, 1..=60).text("Scramble length"));
+                    ui.add(egui::Slider::new(&mut attract.scramble_speed, 0.1..=8.0).text("Scramble speed"));
+                    ui.add(egui::Slider::new(&mut attract.solve_speed, 0.1..=8.0).text("Solve speed"));
+                    ui.add(egui::Slider::new(&mut attract.pause, 0.0..=10.0).text("Pause"));
+                    ui.checkbox(&mut attract.camera_moves, "Camera moves");
+                    ui.add(egui::Slider::new(&mut attract.camera_duration, 0.1..=5.0).text("Camera move time"));
 
-                        ui.separator();
-                        ui.label("Manual Rotation:");
+                    ui.separator();
+                    ui.label("Algorithm:");
+                    ui.text_edit_singleline(&mut self.algorithm_text);
+                    ui.horizontal(|ui| {
+                        if ui.button("Load").clicked() {
+                            let span = self.rubik.state.span;
+                            match Turn::parse_sequence(&self.algorithm_text, span) {
+                                Ok(moves) if moves.is_empty() => {
+                                    self.algorithm_error = Some(String::from("no moves to play"));
+                                }
+                                Ok(moves) => {
+                                    self.player.load(moves, &mut self.rubik);
+                                    self.algorithm_error = None;
+                                }
+                                Err(e) => self.algorithm_error = Some(e),
+                            }
+                        }
+                        if self.player.is_loaded() && ui.button("Close").clicked() {
+                            self.player.unload(&mut self.rubik);
+                        }
+                    });
+                    if let Some(error) = &self.algorithm_error {
+                        ui.label(error);
+                    }
+                    if self.player.is_loaded() {
+                        let player = &mut self.player;
+                        ui.horizontal(|ui| {
+                            if ui.button("|<").clicked() {
+                                player.step_back();
+                            }
+                            if ui.button(if player.playing { "Pause" } else { "Play" }).clicked() {
+                                player.playing = !player.playing;
+                            }
+                            if ui.button(">|").clicked() {
+                                player.step_forward();
+                            }
+                        });
+                        ui.add(egui::Slider::new(&mut player.speed, 0.1..=4.0).text("Speed"));
+                        let mut position = player.position();
+                        let timeline = egui::Slider::new(&mut position, 0.0..=player.len() as f32)
+                            .text("Timeline");
+                        if ui.add(timeline).changed() {
+                            player.scrub(position, &mut self.rubik);
+                        }
+                        let index = (position.floor() as usize).min(player.len() - 1);
+                        ui.label(format!("Move {}: {}", index + 1, player.moves[index]));
+                    }
 
+                    ui.separator();
+                    ui.label("Manual Rotation:");
+                    // the player owns the cube while an algorithm is loaded
+                    ui.add_enabled_ui(!self.player.is_loaded(), |ui| {
                         ui.horizontal(|ui| {
                             if ui.button("U").clicked() {
                                 self.rubik.perform_move(Move::Top);
@@ -208,17 +574,219 @@ impl App {
                             }
                         });
 
-                        ui.separator();
-                        ui.label("Camera Controls:");
-                        ui.label("• Left mouse drag: Orbit");
-                        ui.label("• Mouse wheel: Zoom");
+                        ui.horizontal(|ui| {
+                            if ui.button("Cancel").clicked() {
+                                self.rubik.cancel_move();
+                            }
+                            if ui.button("Reverse").clicked() {
+                                self.rubik.reverse_move();
+                            }
+                            if ui.button("Finish Now").clicked() {
+                                self.rubik.finish_now();
+                            }
+                        });
+                    });
+
+                    ui.separator();
+                    ui.label("Scene:");
+                    ui.horizontal(|ui| {
+                        ui.label("Find");
+                        ui.text_edit_singleline(&mut self.node_query);
+                    });
+                    ui.label("A path like rubik/pieces/piece 1,1,1, or #tag");
+                    let query = self.node_query.trim();
+                    let found: Vec<NodeRef> = if let Some(tag) = query.strip_prefix('#') {
+                        find_by_tag(&renderer.root, tag).collect()
+                    } else if query.is_empty() {
+                        Vec::new()
+                    } else {
+                        find_path(&renderer.root, query).into_iter().collect()
+                    };
+                    ui.label(format!(
+                        "{} found, {} nodes in the scene",
+                        found.len(),
+                        descendants(&renderer.root).count()
+                    ));
+                    egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                        for node in found.iter() {
+                            let p = node.world_transform().w_axis;
+                            let hidden = if is_shown(node, renderer.camera.layers) { "" } else { ", hidden" };
+                            ui.label(format!(
+                                "{} ({:.1}, {:.1}, {:.1}{})",
+                                node_path(node),
+                                p.x,
+                                p.y,
+                                p.z,
+                                hidden
+                            ));
+                        }
+                    });
+                    let (x, y) = self.last_mouse_pos;
+                    let ray = renderer.ray(x, y);
+                    match raycast(&renderer.root, &ray, renderer.camera.layers) {
+                        Some(hit) => {
+                            let (p, n) = (hit.position, hit.normal);
+                            ui.label(format!(
+                                "Under cursor: {} triangle {}",
+                                node_path(&hit.node),
+                                hit.triangle
+                            ));
+                            ui.label(format!(
+                                "at ({:.1}, {:.1}, {:.1}) facing ({:.1}, {:.1}, {:.1})",
+                                p.x, p.y, p.z, n.x, n.y, n.z
+                            ));
+                        }
+                        None => {
+                            ui.label("Under cursor: nothing");
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Export RON").clicked() {
+                            export_scene(&renderer.root, "ron");
+                        }
+                        if ui.button("Export JSON").clicked() {
+                            export_scene(&renderer.root, "json");
+                        }
+                    });
+
+                    ui.separator();
+                    ui.label("Visibility:");
+                    let mut hide_last_layer = self.rubik.hide_last_layer();
+                    if ui.checkbox(&mut hide_last_layer, "Hide last layer").changed() {
+                        self.rubik.set_hide_last_layer(hide_last_layer);
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Layers");
+                        let layers = &mut renderer.camera.layers;
+                        for (layer, label) in [
+                            (LAYER_CORNERS, "Corners"),
+                            (LAYER_EDGES, "Edges"),
+                            (LAYER_CENTERS, "Centers"),
+                            (LAYER_CORE, "Core"),
+                            (LAYER_LIGHT_MARKERS, "Light markers"),
+                        ] {
+                            let mut shown = *layers & layer != 0;
+                            if ui.checkbox(&mut shown, label).changed() {
+                                *layers ^= layer;
+                            }
+                        }
+                    });
+                    // x-ray takes the outside of the cube away to show the core
+                    // pieces inside, turning them as the layers turn
+                    let shell = LAYER_CORNERS | LAYER_EDGES | LAYER_CENTERS;
+                    let layers = &mut renderer.camera.layers;
+                    let mut x_ray = *layers & shell == 0 && *layers & LAYER_CORE != 0;
+                    if ui.checkbox(&mut x_ray, "X-ray").changed() {
+                        if x_ray {
+                            *layers = (*layers & !shell) | LAYER_CORE;
+                        } else {
+                            *layers |= shell;
+                        }
+                    }
+                    ui.checkbox(&mut renderer.culling, "Frustum culling");
+                    let stats = renderer.stats;
+                    ui.label(format!("Drawn {} entities, {} culled", stats.drawn, stats.culled));
+                    let bounds = world_bounds(&self.rubik.root);
+                    if !bounds.is_empty() {
+                        let size = bounds.size();
+                        ui.label(format!("Cube bounds {:.1} x {:.1} x {:.1}", size.x, size.y, size.z));
+                    }
+
+                    ui.separator();
+                    ui.label("Camera Controls:");
+                    ui.label("• Left mouse drag on a sticker: Turn its layer");
+                    ui.label("• Left mouse drag elsewhere: Orbit");
+                    ui.label("• Right mouse drag: Pan");
+                    ui.label("• Double click: Reset the view");
+                    ui.label("• Mouse wheel: Zoom");
+                    ui.label("• Touch: drag to turn or orbit, pinch to zoom, twist to rotate");
 
-                        ui.separator();
-                        let camera = &renderer.camera;
-                        ui.label(format!("Distance: {:.1}", camera.distance));
-                        ui.label(format!("Azimuth: {:.2}", camera.azimuth));
-                        ui.label(format!("Elevation: {:.2}", camera.elevation));
+                    ui.separator();
+                    let camera = &mut renderer.camera;
+                    let mut mode = camera.mode();
+                    egui::ComboBox::from_label("Camera")
+                        .selected_text(format!("{mode:?}"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut mode, CameraMode::Orbit, "Orbit");
+                            ui.selectable_value(&mut mode, CameraMode::Arcball, "Arcball");
+                        });
+                    if mode != camera.mode() {
+                        camera.set_mode(mode);
+                    }
+                    egui::ComboBox::from_label("Projection")
+                        .selected_text(format!("{:?}", camera.projection))
+                        .show_ui(ui, |ui| {
+                            let projection = &mut camera.projection;
+                            ui.selectable_value(projection, Projection::Perspective, "Perspective");
+                            ui.selectable_value(projection, Projection::Orthographic, "Orthographic");
+                        });
+                    ui.horizontal(|ui| {
+                        for view in CameraView::ALL {
+                            if ui.button(view.name()).clicked() {
+                                camera.show_view(view);
+                            }
+                        }
+                        if ui.button("Reset").clicked() {
+                            camera.reset();
+                        }
                     });
+                    ui.checkbox(&mut camera.inertia, "Inertia");
+                    ui.add(egui::Slider::new(&mut camera.damping, 0.5..=10.0).text("Damping"));
+                    ui.label(format!(
+                        "Distance: {:.1} ({:.1} to {:.1})",
+                        camera.distance, camera.min_distance, camera.max_distance
+                    ));
+                    ui.label(format!("Clip planes: {:.2} to {:.1}", camera.near, camera.far));
+                    ui.label(format!("Azimuth: {:.2}", camera.azimuth));
+                    ui.label(format!("Elevation: {:.2}", camera.elevation));
+                    layout_panel(ui, &mut self.layout, renderer, self.rubik.radius());
+                });
+
+            egui::Window::new("Key Bindings")
+                .default_open(false)
+                .show(&self.egui_ctx, |ui| {
+                    keymap_panel(ui, &mut self.keymap, &mut self.rebinding);
+                });
+
+            egui::Window::new("Net")
+                .default_open(false)
+                .show(&self.egui_ctx, |ui| {
+                    ui.checkbox(&mut self.net_overlay, "Also draw it over the cube");
+                    net_panel(ui, &mut self.net, &mut self.rubik, self.paint.as_mut(), renderer);
+                });
+
+            egui::Window::new("Paint")
+                .default_open(false)
+                .show(&self.egui_ctx, |ui| {
+                    let can_paint = !self.show_hypercube && !self.player.is_loaded();
+                    paint_panel(
+                        ui,
+                        &mut self.paint,
+                        &mut self.paint_status,
+                        &mut self.rubik,
+                        &mut self.attract,
+                        renderer,
+                        can_paint,
+                    );
+                });
+
+            if self.show_hypercube {
+                egui::Window::new("Hypercube").show(&self.egui_ctx, |ui| {
+                    let hypercube = &mut self.hypercube;
+                    if ui.button(if hypercube.paused { "Resume" } else { "Pause" }).clicked() {
+                        hypercube.paused = !hypercube.paused;
+                    }
+                    ui.checkbox(&mut hypercube.auto_move, "Auto Move");
+                    if hypercube.state.is_solved() {
+                        ui.label("Solved");
+                    }
+                    ui.separator();
+                    ui.label("4D View:");
+                    let [xw, yw, zw] = &mut hypercube.view_angles;
+                    ui.add(egui::Slider::new(xw, -PI..=PI).text("XW"));
+                    ui.add(egui::Slider::new(yw, -PI..=PI).text("YW"));
+                    ui.add(egui::Slider::new(zw, -PI..=PI).text("ZW"));
+                });
             }
         }
     }
@@ -331,11 +899,22 @@ impl ApplicationHandler<Renderer> for App {
     ) {
         // Pass event to egui first
         let mut egui_consumed = false;
-        if let Some(egui_state) = self.egui_state.as_mut() {
-            if let Some(window) = self.window.as_ref() {
-                let response = egui_state.on_window_event(window, &event);
-                egui_consumed = response.consumed;
-            }
+        if let Some(egui_state) = self.egui_state.as_mut()
+            && let Some(window) = self.window.as_ref()
+        {
+            let response = egui_state.on_window_event(window, &event);
+            egui_consumed = response.consumed;
+        }
+
+        if matches!(
+            event,
+            WindowEvent::KeyboardInput { .. }
+                | WindowEvent::CursorMoved { .. }
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::MouseWheel { .. }
+                | WindowEvent::Touch(_)
+        ) {
+            self.attract.input(&mut self.rubik);
         }
 
         if event == WindowEvent::CloseRequested {
@@ -377,7 +956,10 @@ impl ApplicationHandler<Renderer> for App {
                         renderer.draw(&dummy_ctx, empty_primitives, empty_textures);
                     }
                 },
-                WindowEvent::Resized(size) => renderer.resize(size.width, size.height),
+                WindowEvent::Resized(size) => {
+                    renderer.resize(size.width, size.height);
+                    renderer.camera.frame(self.rubik.radius(), renderer.aspect_ratio());
+                }
                 WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
                     self.egui_ctx.set_pixels_per_point(scale_factor as f32);
                 }
@@ -387,10 +969,46 @@ impl ApplicationHandler<Renderer> for App {
                     is_synthetic: _synthetic,
                 } => {
                     log::info!("keyboard pressed {:?}", event);
+                    let pressed = event.state == ElementState::Pressed && !event.repeat;
+                    let code = match event.physical_key {
+                        PhysicalKey::Code(code) => Some(code),
+                        _ => None,
+                    };
+                    let turn = code.and_then(|code| {
+                        self.keymap.turn(code, self.shift, self.rubik.state.span)
+                    });
+                    if let Some(index) = self.rebinding {
+                        match code {
+                            // escape leaves the binding as it was, once released so
+                            // the release doesn't quit
+                            Some(KeyCode::Escape) if event.state == ElementState::Released => {
+                                self.rebinding = None;
+                            }
+                            // a modifier waits for the key it goes with
+                            Some(code) if pressed && self.keymap.bind(index, code, self.shift) => {
+                                self.rebinding = None;
+                                self.keymap.save();
+                            }
+                            _ => {}
+                        }
+                        return;
+                    }
+                    if let Some(turn) = turn {
+                        let can_turn = !egui_consumed
+                            && !self.show_hypercube
+                            && !self.player.is_loaded()
+                            && self.paint.is_none()
+                            && !self.drag.is_active();
+                        if pressed && can_turn {
+                            self.rubik.turn_now(turn);
+                        }
+                        return;
+                    }
                     match (event.physical_key, event.state) {
                         // space to restart animation
                         (PhysicalKey::Code(KeyCode::Space), ElementState::Released) => {
                             self.start_time_stamp = Instant::now();
+                            self.animator.seek(0.0);
                         }
                         // escape to exit
                         (PhysicalKey::Code(KeyCode::Escape), ElementState::Released) => {
@@ -407,35 +1025,175 @@ impl ApplicationHandler<Renderer> for App {
                         _ => {}
                     }
                 }
-                WindowEvent::MouseInput { state, button, .. } => {
-                    if !egui_consumed && button == MouseButton::Left {
-                        self.mouse_down = state == ElementState::Pressed;
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    self.shift = modifiers.state().shift_key();
+                }
+                WindowEvent::MouseInput { state, button, .. }
+                    if button == MouseButton::Left
+                        && (!egui_consumed || state == ElementState::Released) =>
+                {
+                    self.mouse_down = state == ElementState::Pressed;
+                    if self.mouse_down {
+                        // pressing on a sticker turns its layer, or paints it, anywhere
+                        // else orbits
+                        let (x, y) = self.last_mouse_pos;
+                        let can_turn = !self.show_hypercube && !self.player.is_loaded();
+                        let on_net = net_viewport(renderer, &self.net)
+                            .and_then(|i| renderer.viewport_ray(i, x, y));
+                        let painted = match &self.paint {
+                            Some(_) if on_net.is_none() => facelet_at(&self.rubik, renderer, x, y),
+                            _ => None,
+                        };
+                        if let Some(ray) = on_net {
+                            if let Some(hit) = raycast(&self.net.root, &ray, LAYER_ALL) {
+                                self.net.selected = self.net.facelet(&hit.node);
+                                if let Some(paint) = self.paint.as_mut()
+                                    && let Some(facelet) = self.net.selected
+                                    && paint.paint(&facelet)
+                                {
+                                    repaint_cube(&mut self.rubik, paint, renderer);
+                                }
+                            }
+                        } else if let Some(facelet) = painted {
+                            self.net.selected = Some(facelet);
+                            if let Some(paint) = self.paint.as_mut()
+                                && paint.paint(&facelet)
+                            {
+                                repaint_cube(&mut self.rubik, paint, renderer);
+                            }
+                        } else if can_turn
+                            && self.paint.is_none()
+                            && self.drag.press(x, y, &mut self.rubik, renderer)
+                        {
+                            // a sticker taken hold of is selected on the net too
+                            if let Some((position, normal)) = self.drag.sticker() {
+                                let span = self.rubik.state.span;
+                                self.net.selected = Facelet::from_sticker(position, normal, span);
+                            }
+                        } else {
+                            let double = self
+                                .last_click
+                                .is_some_and(|last| last.elapsed().as_secs_f32() < DOUBLE_CLICK);
+                            if double {
+                                renderer.camera.reset();
+                                self.last_click = None;
+                            } else {
+                                renderer.camera.grab();
+                                self.last_click = Some(Instant::now());
+                            }
+                        }
+                    } else {
+                        self.drag.release(&mut self.rubik);
+                        renderer.camera.release();
                     }
                 }
+                WindowEvent::MouseInput { state, button, .. }
+                    if button == MouseButton::Right
+                        && (!egui_consumed || state == ElementState::Released) =>
+                {
+                    self.panning = state == ElementState::Pressed;
+                }
                 WindowEvent::CursorMoved { position, .. } => {
                     let current_pos = (position.x as f32, position.y as f32);
 
-                    if !egui_consumed && self.mouse_down {
+                    if self.drag.is_active() {
+                        let (x, y) = current_pos;
+                        self.drag.drag(x, y, &mut self.rubik, renderer);
+                    } else if !egui_consumed && (self.mouse_down || self.panning) {
                         let delta_x = current_pos.0 - self.last_mouse_pos.0;
                         let delta_y = current_pos.1 - self.last_mouse_pos.1;
+                        let delta = Vec2::new(delta_x, delta_y);
 
-                        renderer.camera.orbit(-delta_x * 0.01, delta_y * 0.01);
+                        if self.panning {
+                            renderer.camera.pan(delta, renderer.view_rect()[3]);
+                        } else {
+                            renderer.camera.drag(delta);
+                        }
                     }
 
                     self.last_mouse_pos = current_pos;
                 }
-                WindowEvent::MouseWheel { delta, .. } => {
-                    if !egui_consumed {
-                        use winit::event::MouseScrollDelta;
-                        let zoom_delta = match delta {
-                            MouseScrollDelta::LineDelta(_, y) => y * 2.0,
-                            MouseScrollDelta::PixelDelta(pos) => pos.y as f32 * 0.05,
-                        };
-                        renderer.camera.zoom(-zoom_delta);
+                WindowEvent::Touch(touch) => {
+                    // fingers landing on the debug panels belong to egui
+                    if egui_consumed && touch.phase == TouchPhase::Started {
+                        return;
+                    }
+                    let can_turn = !self.show_hypercube && !self.player.is_loaded();
+                    match self.touches.touch(&touch) {
+                        Some(Gesture::Press(p)) if self.paint.is_some() => {
+                            // a finger paints the sticker it lands on instead of turning it
+                            let facelet = facelet_at(&self.rubik, renderer, p.x, p.y);
+                            if let Some(paint) = self.paint.as_mut()
+                                && let Some(facelet) = facelet
+                            {
+                                self.net.selected = Some(facelet);
+                                if paint.paint(&facelet) {
+                                    repaint_cube(&mut self.rubik, paint, renderer);
+                                }
+                            } else {
+                                renderer.camera.grab();
+                            }
+                        }
+                        Some(Gesture::Press(p)) => {
+                            let on_cube = can_turn && self.drag.press(p.x, p.y, &mut self.rubik, renderer);
+                            if !on_cube {
+                                renderer.camera.grab();
+                            }
+                        }
+                        Some(Gesture::Drag { position, delta }) => {
+                            if self.drag.is_active() {
+                                self.drag.drag(position.x, position.y, &mut self.rubik, renderer);
+                            } else {
+                                renderer.camera.drag(delta);
+                            }
+                        }
+                        Some(Gesture::Release) => {
+                            self.drag.release(&mut self.rubik);
+                            renderer.camera.release();
+                        }
+                        Some(Gesture::Pinch { scale, rotation }) => {
+                            let camera = &mut renderer.camera;
+                            camera.zoom(camera.distance * (1.0 / scale - 1.0));
+                            // clockwise on screen is clockwise seen from the eye
+                            let view = (camera.get_eye_position() - camera.target).normalize();
+                            self.cube_twist = Quat::from_axis_angle(view, -rotation) * self.cube_twist;
+                        }
+                        _ => {}
                     }
                 }
+                WindowEvent::MouseWheel { delta, .. } if !egui_consumed => {
+                    use winit::event::MouseScrollDelta;
+                    let zoom_delta = match delta {
+                        MouseScrollDelta::LineDelta(_, y) => y * 2.0,
+                        MouseScrollDelta::PixelDelta(pos) => pos.y as f32 * 0.05,
+                    };
+                    renderer.camera.zoom(-zoom_delta);
+                }
                 _ => {}
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::new_group;
+
+    #[test]
+    fn light_loop_meets_its_start() {
+        for time_offset in [0.0, 2200.0, 6200.0] {
+            let (wander, spin) = light_animations(new_group(), new_group(), time_offset);
+            let (path, spin) = (wander.translation.unwrap(), spin.rotation.unwrap());
+            assert_eq!(path.duration(), LIGHT_LOOP);
+            assert!(path.sample(0.0).abs_diff_eq(path.sample(LIGHT_LOOP), 1e-3));
+            assert!(spin.sample(0.0).abs_diff_eq(spin.sample(LIGHT_LOOP), 1e-3));
+            // the last stretch moves no further than any other
+            let step = |t: f32| path.sample(t).distance(path.sample(t - LIGHT_KEY_STEP));
+            let longest = (1..(LIGHT_LOOP / LIGHT_KEY_STEP) as u32)
+                .map(|i| step(i as f32 * LIGHT_KEY_STEP))
+                .fold(0.0, f32::max);
+            assert!(step(LIGHT_LOOP) <= longest);
+        }
+    }
+}
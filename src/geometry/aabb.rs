@@ -0,0 +1,92 @@
+use glam::{Mat4, Vec3};
+
+/// Axis-aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Contains nothing, the starting point for unions
+    pub const EMPTY: Aabb = Aabb {
+        min: Vec3::INFINITY,
+        max: Vec3::NEG_INFINITY,
+    };
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        points.into_iter().fold(Self::EMPTY, |aabb, p| Self {
+            min: aabb.min.min(p),
+            max: aabb.max.max(p),
+        })
+    }
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+    /// Box around this one once moved by `transform`, fitting all 8 corners
+    pub fn transformed(&self, transform: &Mat4) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        Self::from_points((0..8).map(|i| {
+            let corner = Vec3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            );
+            transform.transform_point3(corner)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Quat;
+    use std::f32::consts::FRAC_PI_4;
+
+    #[test]
+    fn from_points_and_union() {
+        let a = Aabb::from_points([Vec3::new(1.0, -2.0, 0.0), Vec3::new(-1.0, 3.0, 2.0)]);
+        assert_eq!(a.min, Vec3::new(-1.0, -2.0, 0.0));
+        assert_eq!(a.max, Vec3::new(1.0, 3.0, 2.0));
+        assert_eq!(a.center(), Vec3::new(0.0, 0.5, 1.0));
+        assert_eq!(a.size(), Vec3::new(2.0, 5.0, 2.0));
+        let b = Aabb::from_points([Vec3::splat(4.0)]);
+        let both = a.union(&b);
+        assert_eq!(both.min, a.min);
+        assert_eq!(both.max, Vec3::splat(4.0));
+    }
+
+    #[test]
+    fn empty_box() {
+        assert!(Aabb::EMPTY.is_empty());
+        assert!(Aabb::from_points([]).is_empty());
+        assert!(!Aabb::from_points([Vec3::ZERO]).is_empty());
+        let a = Aabb::from_points([Vec3::ONE]);
+        assert_eq!(Aabb::EMPTY.union(&a), a);
+        assert!(Aabb::EMPTY.transformed(&Mat4::from_scale(Vec3::splat(2.0))).is_empty());
+    }
+
+    #[test]
+    fn transformed_fits_every_corner() {
+        let a = Aabb::from_points([Vec3::splat(-1.0), Vec3::splat(1.0)]);
+        let moved = a.transformed(&Mat4::from_translation(Vec3::new(5.0, 0.0, 0.0)));
+        assert_eq!(moved.center(), Vec3::new(5.0, 0.0, 0.0));
+        let turned = a.transformed(&Mat4::from_quat(Quat::from_rotation_z(FRAC_PI_4)));
+        let half = 2.0_f32.sqrt();
+        assert!(turned.max.abs_diff_eq(Vec3::new(half, half, 1.0), 1e-5));
+        assert!(turned.min.abs_diff_eq(-Vec3::new(half, half, 1.0), 1e-5));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::geometry::Mesh;
+use crate::geometry::MeshSource;
 use crate::geometry::Vertex;
 use wgpu::Device;
 
@@ -44,6 +45,9 @@ impl Mesh {
             16, 17, 18, 18, 19, 16, // front
             20, 21, 22, 22, 23, 20, // back
         ];
-        Self::new(vertex_data.to_vec(), index_data.to_vec(), device)
+        Self {
+            source: MeshSource::Cube { color: col },
+            ..Self::new(vertex_data.to_vec(), index_data.to_vec(), device)
+        }
     }
 }
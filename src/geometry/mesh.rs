@@ -1,12 +1,53 @@
-use crate::geometry::Vertex;
+use crate::geometry::{Aabb, Vertex};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 use wgpu::{Buffer, BufferUsages, Device};
 
+/// How a mesh was built, enough to build it again when loading a scene
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MeshSource {
+    Cube {
+        color: u32,
+    },
+    RubikPiece {
+        faced: [bool; 6],
+        sticker_color: Option<u32>,
+        size: Vec3,
+    },
+    PaintedPiece {
+        colors: [u32; 6],
+        size: Vec3,
+    },
+    /// Made from raw vertices, there's no recipe to rebuild it from
+    Custom,
+}
+
+impl MeshSource {
+    pub fn build(&self, device: &Device) -> Option<Mesh> {
+        match self {
+            MeshSource::Cube { color } => Some(Mesh::new_cube(*color, device)),
+            MeshSource::RubikPiece {
+                faced,
+                sticker_color,
+                size,
+            } => Some(Mesh::new_rubik_piece(device, *faced, *sticker_color, *size)),
+            MeshSource::PaintedPiece { colors, size } => {
+                Some(Mesh::new_painted_piece(device, *colors, *size))
+            }
+            MeshSource::Custom => None,
+        }
+    }
+}
+
 pub struct Mesh {
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
     pub vertex_buffer: Buffer,
     pub index_buffer: Buffer,
+    pub source: MeshSource,
+    /// Box around `vertices` in the mesh's own space
+    pub bounds: Aabb,
 }
 
 impl Mesh {
@@ -21,11 +62,17 @@ impl Mesh {
             contents: bytemuck::cast_slice(&indices),
             usage: BufferUsages::INDEX,
         });
+        let bounds = Aabb::from_points(vertices.iter().map(|v| {
+            let [x, y, z, _] = v.position;
+            Vec3::new(x, y, z)
+        }));
         Self {
+            bounds,
             vertices,
             indices,
             vertex_buffer,
             index_buffer,
+            source: MeshSource::Custom,
         }
     }
 }
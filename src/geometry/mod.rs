@@ -1,6 +1,8 @@
+pub mod aabb;
 pub mod cube;
 pub mod mesh;
 pub mod rubik_piece;
 pub mod vertex;
-pub use mesh::Mesh;
+pub use aabb::Aabb;
+pub use mesh::{Mesh, MeshSource};
 pub use vertex::Vertex;
@@ -1,30 +1,51 @@
 use crate::geometry::Mesh;
+use crate::geometry::MeshSource;
 use crate::geometry::Vertex;
+use glam::Vec3;
 use wgpu::Device;
 
+/// Sticker colours in top, bottom, left, right, front, back order
+pub const FACE_COLORS: [u32; 6] = [
+    0xf9e2afff, // top - yellow
+    0xf8fafcff, // bottom - white
+    0x89b4faff, // left - purple
+    0x40a02bff, // right - green
+    0xef4444ff, // front - red
+    0xfe640bff, // back - orange
+];
+pub const FACE_COLOR_NAMES: [&str; 6] = ["yellow", "white", "purple", "green", "red", "orange"];
+/// Colour of the sides of a piece with no sticker
+pub const PIECE_COLOR: u32 = 0x040407ff;
+
 impl Mesh {
+    /// `faced` lists which sides get a sticker, in top, bottom, left, right,
+    /// front, back order. `sticker_color` overrides the face colours.
     pub fn new_rubik_piece(
         device: &Device,
-        faced_top: bool,
-        faced_bottom: bool,
-        faced_left: bool,
-        faced_right: bool,
-        faced_front: bool,
-        faced_back: bool,
+        faced: [bool; 6],
+        sticker_color: Option<u32>,
+        size: Vec3,
     ) -> Self {
-        const GREEN: u32 = 0x40a02bff; // right - green
-        const PURPLE: u32 = 0x89b4faff; // left - purple
-        const YELLOW: u32 = 0xf9e2afff; // top - yellow
-        const WHITE: u32 = 0xf8fafcff; // bottom - white
-        const RED: u32 = 0xef4444ff; // front - red
-        const ORANGE: u32 = 0xfe640bff; // back - orange
-        const BLACK: u32 = 0x040407ff; // black
-        let top_color = if faced_top { YELLOW } else { BLACK };
-        let bottom_color = if faced_bottom { WHITE } else { BLACK };
-        let left_color = if faced_left { PURPLE } else { BLACK };
-        let right_color = if faced_right { GREEN } else { BLACK };
-        let front_color = if faced_front { RED } else { BLACK };
-        let back_color = if faced_back { ORANGE } else { BLACK };
+        let colors = std::array::from_fn(|i| {
+            if faced[i] {
+                sticker_color.unwrap_or(FACE_COLORS[i])
+            } else {
+                PIECE_COLOR
+            }
+        });
+        Self {
+            source: MeshSource::RubikPiece {
+                faced,
+                sticker_color,
+                size,
+            },
+            ..Self::new_painted_piece(device, colors, size)
+        }
+    }
+    /// A piece with each side in its own colour, in top, bottom, left, right,
+    /// front, back order
+    pub fn new_painted_piece(device: &Device, colors: [u32; 6], size: Vec3) -> Self {
+        let [top_color, bottom_color, left_color, right_color, front_color, back_color] = colors;
         let vertex_data = [
             // top (0, 0, 1)
             Vertex::new([-1.0, -1.0, 1.0], [0.0, 0.0, 1.0], top_color),
@@ -65,6 +86,16 @@ impl Mesh {
             16, 17, 18, 18, 19, 16, // front
             20, 21, 22, 22, 23, 20, // back
         ];
-        Self::new(vertex_data.to_vec(), index_data.to_vec(), device)
+        let half = size * 0.5;
+        let vertex_data = vertex_data.map(|mut vertex| {
+            vertex.position[0] *= half.x;
+            vertex.position[1] *= half.y;
+            vertex.position[2] *= half.z;
+            vertex
+        });
+        Self {
+            source: MeshSource::PaintedPiece { colors, size },
+            ..Self::new(vertex_data.to_vec(), index_data.to_vec(), device)
+        }
     }
 }
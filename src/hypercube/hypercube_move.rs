@@ -0,0 +1,96 @@
+use glam::{IVec4, Mat4};
+
+/// Twist of one of the 8 cells of the hypercube. The twist rotates the cell
+/// in the plane going from axis `from` to axis `to`, both of which must lie
+/// inside the cell, so the cell's own axis stays put.
+#[derive(Clone, Copy)]
+pub struct HyperTurn {
+    pub cell_axis: usize,
+    pub cell_side: i32,
+    pub from: usize,
+    pub to: usize,
+    pub quarter_turns: i32,
+}
+
+impl HyperTurn {
+    pub const NONE: HyperTurn = HyperTurn {
+        cell_axis: 0,
+        cell_side: 0,
+        from: 0,
+        to: 0,
+        quarter_turns: 0,
+    };
+    pub fn new(cell_axis: usize, cell_side: i32, from: usize, to: usize, quarter_turns: i32) -> Self {
+        Self {
+            cell_axis,
+            cell_side,
+            from,
+            to,
+            quarter_turns,
+        }
+    }
+    pub fn is_none(&self) -> bool {
+        self.cell_side == 0
+    }
+    /// Normal of the cell being twisted
+    pub fn cell(&self) -> IVec4 {
+        let mut normal = IVec4::ZERO;
+        normal[self.cell_axis] = self.cell_side;
+        normal
+    }
+}
+
+/// Rotation in the plane going from axis `from` to axis `to`
+pub fn plane_rotation(from: usize, to: usize, angle: f32) -> Mat4 {
+    let (s, c) = angle.sin_cos();
+    let mut cols = Mat4::IDENTITY.to_cols_array_2d();
+    cols[from][from] = c;
+    cols[from][to] = s;
+    cols[to][from] = -s;
+    cols[to][to] = c;
+    Mat4::from_cols_array_2d(&cols)
+}
+
+/// Integer version of `plane_rotation` by quarter turns
+pub fn rotate_plane(v: IVec4, from: usize, to: usize, quarter_turns: i32) -> IVec4 {
+    let mut v = v;
+    for _ in 0..quarter_turns.rem_euclid(4) {
+        let (a, b) = (v[from], v[to]);
+        v[from] = -b;
+        v[to] = a;
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec4;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn four_quarter_turns_go_round() {
+        let v = IVec4::new(1, -1, 0, 1);
+        for (from, to) in [(0, 1), (1, 3), (3, 2), (2, 0)] {
+            let mut turned = v;
+            for i in 1..=4 {
+                turned = rotate_plane(turned, from, to, 1);
+                assert_eq!(turned == v, i == 4);
+            }
+            assert_eq!(rotate_plane(v, from, to, -1), rotate_plane(v, from, to, 3));
+        }
+    }
+
+    #[test]
+    fn plane_rotation_matches_quarter_turns() {
+        let v = IVec4::new(1, 2, -1, 3);
+        for (from, to) in [(0, 1), (0, 3), (1, 2), (3, 2)] {
+            let matrix = plane_rotation(from, to, FRAC_PI_2);
+            let turned = matrix * v.as_vec4();
+            let expected: Vec4 = rotate_plane(v, from, to, 1).as_vec4();
+            assert!(turned.abs_diff_eq(expected, 1e-5), "{from} {to}");
+        }
+        // a quarter turn takes `from` onto `to`
+        assert_eq!(rotate_plane(IVec4::X, 0, 1, 1), IVec4::Y);
+    }
+}
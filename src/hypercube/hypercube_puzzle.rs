@@ -0,0 +1,194 @@
+use crate::geometry::Mesh;
+use crate::hypercube::hypercube_move::plane_rotation;
+use crate::hypercube::hypercube_state::cell_normals;
+use crate::hypercube::{HyperTurn, HypercubeState};
+use crate::material::ShaderLit;
+use crate::world::{Clip, Easing, Node, NodeRef, Renderer, Track, new_entity, new_group};
+use glam::{Mat3, Mat4, Quat, Vec3, Vec4};
+use rand::Rng;
+use std::rc::Rc;
+
+/// How far a cell sits from the center in 4D
+const CELL_OFFSET: f32 = 1.5;
+/// Pull the stickers of a cell together so cells don't touch
+const CELL_SHRINK: f32 = 0.8;
+/// Size of a sticker relative to its slot in the cell
+const STICKER_SHRINK: f32 = 0.7;
+/// Distance of the 4D eye from the center, along w
+const EYE_DISTANCE: f32 = 5.0;
+/// Cells facing the 4D eye wrap around everything else, hide them
+const HIDE_FACING: f32 = 0.5;
+const WORLD_SCALE: f32 = 3.0;
+
+pub struct Hypercube {
+    /// Angle of the turn in progress over time
+    angle: Clip<f32>,
+    current_turn: HyperTurn,
+    pub root: NodeRef,
+    /// Cubie index in `state`, sticker index in that cubie and the node drawing it
+    stickers: Vec<(usize, usize, NodeRef)>,
+    pub state: HypercubeState,
+    /// 4D view rotation, in the xw, yw and zw planes
+    pub view_angles: [f32; 3],
+    pub paused: bool,
+    pub auto_move: bool,
+}
+
+impl Hypercube {
+    pub fn new() -> Self {
+        Self {
+            angle: Clip::new(Track::new(0.0)),
+            current_turn: HyperTurn::NONE,
+            root: {
+                let mut root = new_group();
+                root.set_name("hypercube");
+                root
+            },
+            stickers: Vec::new(),
+            state: HypercubeState::new(),
+            view_angles: [0.3, 0.2, 0.0],
+            paused: false,
+            auto_move: true,
+        }
+    }
+    pub fn generate_pieces(&mut self, renderer: &Renderer) {
+        const COLORS: [u32; 8] = [
+            0x40a02bff, 0x89b4faff, 0xef4444ff, 0xfe640bff, 0xf9e2afff, 0xf8fafcff, 0xf5c2e7ff,
+            0x94e2d5ff,
+        ];
+        self.root.extract_all_child();
+        self.stickers = Vec::new();
+        self.state = HypercubeState::new();
+        self.current_turn = HyperTurn::NONE;
+        let shader = Rc::new(ShaderLit::new(renderer));
+        let meshes: Vec<(Vec4, Rc<Mesh>)> = cell_normals()
+            .iter()
+            .zip(COLORS)
+            .map(|(normal, color)| {
+                (
+                    normal.as_vec4(),
+                    Rc::new(Mesh::new_cube(color, &renderer.device)),
+                )
+            })
+            .collect();
+        for (i, cubie) in self.state.cubies.iter().enumerate() {
+            for (j, sticker) in cubie.stickers.iter().enumerate() {
+                let color = sticker.color.as_vec4();
+                let Some((_, mesh)) = meshes.iter().find(|(normal, _)| *normal == color) else {
+                    continue;
+                };
+                let mut node = new_entity(mesh.clone(), shader.clone());
+                node.set_name(&format!("sticker {i}.{j}"));
+                node.add_tag("sticker");
+                self.root.add_child(node.clone());
+                self.stickers.push((i, j, node));
+            }
+        }
+    }
+    pub fn start_move_random(&mut self) {
+        let mut rng = rand::rng();
+        let cell_axis = rng.random_range(0..4);
+        let cell_side = if rng.random_bool(0.5) { 1 } else { -1 };
+        let mut plane: Vec<usize> = (0..4).filter(|axis| *axis != cell_axis).collect();
+        plane.remove(rng.random_range(0..3));
+        if rng.random_bool(0.5) {
+            plane.swap(0, 1);
+        }
+        let quarter_turns = rng.random_range(1..=2);
+        self.perform_move(HyperTurn::new(
+            cell_axis,
+            cell_side,
+            plane[0],
+            plane[1],
+            quarter_turns,
+        ));
+    }
+    pub fn perform_move(&mut self, turn: HyperTurn) {
+        if !self.angle.is_finished() {
+            return;
+        }
+        self.current_turn = turn;
+        let angle = std::f32::consts::PI * 0.5 * turn.quarter_turns as f32;
+        let duration = 0.6 + 0.3 * turn.quarter_turns as f32;
+        self.angle = Clip::new(Track::tween(0.0, angle, duration, Easing::CubicInOut));
+    }
+    pub fn finish_move(&mut self) {
+        self.state.apply(self.current_turn);
+        self.current_turn = HyperTurn::NONE;
+        if self.auto_move {
+            self.start_move_random();
+        }
+    }
+    fn view_matrix(&self) -> Mat4 {
+        let [xw, yw, zw] = self.view_angles;
+        plane_rotation(0, 3, xw) * plane_rotation(1, 3, yw) * plane_rotation(2, 3, zw)
+    }
+    pub fn update(&mut self, delta_time: f32) {
+        let alpha = self
+            .angle
+            .move_by(if self.paused { 0.0 } else { delta_time });
+        if !self.paused && self.angle.is_finished() {
+            self.finish_move();
+            self.project(0.0);
+        } else {
+            self.project(alpha);
+        }
+    }
+    /// Place every sticker node according to the logical state, the turn in
+    /// progress rotated by `alpha` and the 4D view
+    fn project(&mut self, alpha: f32) {
+        let view = self.view_matrix();
+        let turning = self.state.layer(self.current_turn);
+        let twist = if self.current_turn.is_none() {
+            Mat4::IDENTITY
+        } else {
+            plane_rotation(self.current_turn.from, self.current_turn.to, alpha)
+        };
+        for (i, j, node) in self.stickers.iter_mut() {
+            let cubie = &self.state.cubies[*i];
+            let normal = cubie.stickers[*j].normal;
+            let axis = normal.abs().to_array().iter().position(|v| *v != 0).unwrap_or(0);
+            let position = cubie.position.as_vec4();
+            let normal = normal.as_vec4();
+            let mut center = normal * CELL_OFFSET + (position - normal) * CELL_SHRINK;
+            let mut tangents: Vec<Vec4> = (0..4)
+                .filter(|a| *a != axis)
+                .map(|a| Vec4::AXES[a] * CELL_SHRINK * STICKER_SHRINK * 0.5)
+                .collect();
+            let mut facing = normal;
+            if turning.contains(i) {
+                center = twist * center;
+                facing = twist * facing;
+                tangents.iter_mut().for_each(|t| *t = twist * *t);
+            }
+            center = view * center;
+            facing = view * facing;
+            if facing.w > HIDE_FACING {
+                node.scale_uniform(0.0);
+                continue;
+            }
+            let k = EYE_DISTANCE / (EYE_DISTANCE - center.w) * WORLD_SCALE;
+            let translation = center.truncate() * k;
+            let edges: Vec<Vec3> = tangents
+                .iter()
+                .map(|t| (view * *t).truncate() * k)
+                .collect();
+            // the projected edges are not quite orthogonal, make them so
+            let x = edges[0].normalize_or_zero();
+            let y = (edges[1] - x * x.dot(edges[1])).normalize_or_zero();
+            let z = x.cross(y);
+            let rotation = if z == Vec3::ZERO {
+                Quat::IDENTITY
+            } else {
+                Quat::from_mat3(&Mat3::from_cols(x, y, z))
+            };
+            node.translate(translation.x, translation.y, translation.z);
+            node.rotate_quat(rotation);
+            node.scale(
+                edges[0].length(),
+                edges[1].length(),
+                edges[2].length(),
+            );
+        }
+    }
+}
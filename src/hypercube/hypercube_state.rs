@@ -0,0 +1,149 @@
+use crate::hypercube::HyperTurn;
+use crate::hypercube::hypercube_move::rotate_plane;
+use glam::IVec4;
+
+#[derive(Clone, Copy)]
+pub struct HyperSticker {
+    /// Cell the sticker is on right now
+    pub normal: IVec4,
+    /// Cell the sticker belongs to once solved, used as its colour
+    pub color: IVec4,
+}
+
+#[derive(Clone)]
+pub struct HyperCubie {
+    pub position: IVec4,
+    pub stickers: Vec<HyperSticker>,
+}
+
+/// Logical model of the 3x3x3x3 hypercube
+pub struct HypercubeState {
+    pub cubies: Vec<HyperCubie>,
+}
+
+/// Outward normals of the 8 cells, 2 per axis
+pub fn cell_normals() -> [IVec4; 8] {
+    [
+        IVec4::X,
+        IVec4::NEG_X,
+        IVec4::Y,
+        IVec4::NEG_Y,
+        IVec4::Z,
+        IVec4::NEG_Z,
+        IVec4::W,
+        IVec4::NEG_W,
+    ]
+}
+
+impl HypercubeState {
+    pub fn new() -> Self {
+        let mut cubies = Vec::new();
+        for w in -1..=1 {
+            for z in -1..=1 {
+                for y in -1..=1 {
+                    for x in -1..=1 {
+                        let home = IVec4::new(x, y, z, w);
+                        let stickers: Vec<HyperSticker> = cell_normals()
+                            .into_iter()
+                            .filter(|normal| home.dot(*normal) == 1)
+                            .map(|normal| HyperSticker {
+                                normal,
+                                color: normal,
+                            })
+                            .collect();
+                        if stickers.is_empty() {
+                            continue;
+                        }
+                        cubies.push(HyperCubie {
+                            position: home,
+                            stickers,
+                        });
+                    }
+                }
+            }
+        }
+        Self { cubies }
+    }
+    /// Indices of the cubies `turn` moves
+    pub fn layer(&self, turn: HyperTurn) -> Vec<usize> {
+        if turn.is_none() {
+            return Vec::new();
+        }
+        let cell = turn.cell();
+        self.cubies
+            .iter()
+            .enumerate()
+            .filter(|(_, cubie)| cubie.position.dot(cell) == 1)
+            .map(|(i, _)| i)
+            .collect()
+    }
+    pub fn apply(&mut self, turn: HyperTurn) {
+        for i in self.layer(turn) {
+            let cubie = &mut self.cubies[i];
+            cubie.position = rotate_plane(cubie.position, turn.from, turn.to, turn.quarter_turns);
+            for sticker in cubie.stickers.iter_mut() {
+                sticker.normal =
+                    rotate_plane(sticker.normal, turn.from, turn.to, turn.quarter_turns);
+            }
+        }
+    }
+    /// Every cell shows a single colour
+    pub fn is_solved(&self) -> bool {
+        let mut cells: Vec<(IVec4, IVec4)> = Vec::new();
+        for sticker in self.cubies.iter().flat_map(|cubie| cubie.stickers.iter()) {
+            match cells.iter().find(|(normal, _)| *normal == sticker.normal) {
+                Some((_, color)) if *color != sticker.color => return false,
+                Some(_) => {}
+                None => cells.push((sticker.normal, sticker.color)),
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_state_is_solved() {
+        let state = HypercubeState::new();
+        // every piece of the 3x3x3x3 but the hidden core
+        assert_eq!(state.cubies.len(), 80);
+        assert!(state.is_solved());
+    }
+
+    #[test]
+    fn four_quarter_twists_come_back() {
+        for turn in [HyperTurn::new(0, 1, 1, 2, 1), HyperTurn::new(3, -1, 0, 2, -1)] {
+            let mut state = HypercubeState::new();
+            for i in 0..4 {
+                state.apply(turn);
+                assert_eq!(state.is_solved(), i == 3);
+            }
+        }
+    }
+
+    #[test]
+    fn twists_undo_in_reverse() {
+        let turns = [
+            HyperTurn::new(0, 1, 1, 2, 1),
+            HyperTurn::new(3, -1, 0, 2, 2),
+            HyperTurn::new(1, 1, 3, 0, 1),
+            HyperTurn::new(2, -1, 1, 3, -1),
+        ];
+        let mut state = HypercubeState::new();
+        for turn in turns {
+            state.apply(turn);
+        }
+        assert!(!state.is_solved());
+        for turn in turns.iter().rev() {
+            state.apply(HyperTurn {
+                quarter_turns: -turn.quarter_turns,
+                ..*turn
+            });
+        }
+        assert!(state.is_solved());
+        assert!(state.layer(HyperTurn::NONE).is_empty());
+    }
+}
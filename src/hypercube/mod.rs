@@ -0,0 +1,6 @@
+pub mod hypercube_move;
+pub mod hypercube_puzzle;
+pub mod hypercube_state;
+pub use hypercube_move::HyperTurn;
+pub use hypercube_puzzle::Hypercube;
+pub use hypercube_state::HypercubeState;
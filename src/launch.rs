@@ -0,0 +1,73 @@
+/// What the app was started with: `--seed <n>` and `--scene <path>` on the
+/// command line on native, `?seed=<n>` in the page URL on the web
+#[derive(Default)]
+pub struct LaunchOptions {
+    /// Seed for the cube's random moves, to replay an earlier run
+    pub seed: Option<u64>,
+    /// Scene file to load instead of the default one
+    pub scene: Option<String>,
+}
+
+impl LaunchOptions {
+    /// Read `--name value` and `--name=value` options, a later one wins.
+    /// Anything else is skipped.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Self {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                continue;
+            };
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (option, args.next()),
+            };
+            match name {
+                "seed" => options.seed = value.and_then(|value| value.parse().ok()),
+                "scene" => options.scene = value.or(options.scene),
+                _ => {}
+            }
+        }
+        options
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_env() -> Self {
+        Self::parse(std::env::args().skip(1))
+    }
+    /// The page URL's query pairs read as `--name=value` options
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_env() -> Self {
+        let search = web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .unwrap_or_default();
+        let pairs = search.trim_start_matches('?').split('&');
+        Self::parse(pairs.map(|pair| format!("--{pair}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> LaunchOptions {
+        LaunchOptions::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn reads_both_forms() {
+        let options = parse("--seed 42 --scene=lights.json");
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.scene.as_deref(), Some("lights.json"));
+        let options = parse("--scene a.ron --seed=7");
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.scene.as_deref(), Some("a.ron"));
+    }
+
+    #[test]
+    fn skips_what_it_does_not_know() {
+        let options = parse("run --verbose --seed nope --scene");
+        assert_eq!(options.seed, None);
+        assert_eq!(options.scene, None);
+        assert_eq!(parse("--seed=1 --seed 2").seed, Some(2));
+    }
+}
@@ -1,9 +1,14 @@
 mod app;
 mod geometry;
+mod hypercube;
+mod launch;
 mod material;
 mod rubik;
+mod settings;
+mod touch_gestures;
 mod world;
 pub use app::App;
+pub use launch::LaunchOptions;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
@@ -46,6 +51,6 @@ pub fn run() {
     init_logger();
     let event_loop = EventLoop::with_user_event().build().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
-    let mut app = App::new(&event_loop);
+    let mut app = App::new(&event_loop, LaunchOptions::from_env());
     event_loop.run_app(&mut app).unwrap();
 }
@@ -1,6 +1,6 @@
 pub mod shader;
 pub mod shader_lit;
 pub mod shader_unlit;
-pub use shader::Shader;
+pub use shader::{Shader, ShaderKind};
 pub use shader_lit::ShaderLit;
 pub use shader_unlit::ShaderUnlit;
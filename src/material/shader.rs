@@ -1,12 +1,27 @@
 use wgpu::{BufferAddress, Queue, RenderPass};
 
 use crate::world::Light;
+use serde::{Deserialize, Serialize};
+
+/// Which shader a node is drawn with, for saving and loading scenes
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ShaderKind {
+    Lit,
+    Unlit,
+}
 
 pub trait Shader {
-    fn set_pipeline<'a>(&'a self, pass: &mut RenderPass<'a>, offset: BufferAddress);
+    fn kind(&self) -> ShaderKind;
+    /// Bind the node uniforms at `offset` and the camera at `camera_offset`
+    fn set_pipeline<'a>(
+        &'a self,
+        pass: &mut RenderPass<'a>,
+        offset: BufferAddress,
+        camera_offset: BufferAddress,
+    );
     fn write_transform_data(&self, queue: &Queue, offset: BufferAddress, matrix: &[f32; 16]);
     fn write_rotation_data(&self, queue: &Queue, offset: BufferAddress, matrix: &[f32; 16]);
     fn write_time_data(&self, queue: &Queue, time: f32);
-    fn write_camera_data(&self, queue: &Queue, matrix: &[f32; 16]);
+    fn write_camera_data(&self, queue: &Queue, offset: BufferAddress, matrix: &[f32; 16]);
     fn write_light_data(&self, queue: &Queue, lights: &[Light]);
 }
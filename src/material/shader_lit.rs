@@ -1,4 +1,4 @@
-use crate::material::Shader;
+use crate::material::{Shader, ShaderKind};
 use glam::Mat4;
 use std::borrow::Cow;
 use std::mem::size_of;
@@ -6,7 +6,7 @@ use std::mem::size_of;
 use std::time::Instant;
 #[cfg(target_arch = "wasm32")]
 use web_time::Instant;
-use wgpu::util::{align_to, BufferInitDescriptor, DeviceExt};
+use wgpu::util::align_to;
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
     BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferAddress, BufferBinding,
@@ -18,7 +18,7 @@ use wgpu::{
 };
 
 use crate::geometry::Vertex;
-use crate::world::{Light, Renderer, MAX_ENTITY, MAX_LIGHT};
+use crate::world::{Light, Renderer, MAX_ENTITY, MAX_LIGHT, MAX_VIEWPORT};
 
 pub struct ShaderLit {
     pub render_pipeline: RenderPipeline,
@@ -43,7 +43,7 @@ impl ShaderLit {
                         visibility: ShaderStages::VERTEX,
                         ty: BindingType::Buffer {
                             ty: BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
+                            has_dynamic_offset: true,
                             min_binding_size: BufferSize::new(size_of::<Mat4>() as u64),
                         },
                         count: None,
@@ -135,10 +135,17 @@ impl ShaderLit {
             multiview: None,
             cache: None,
         });
-        let vp_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        let camera_uniform_size = size_of::<Mat4>() as BufferAddress;
+        let camera_uniform_aligned = {
+            let alignment = device.limits().min_uniform_buffer_offset_alignment as BufferAddress;
+            align_to(camera_uniform_size, alignment)
+        };
+        // one view projection per viewport
+        let vp_buffer = device.create_buffer(&BufferDescriptor {
             label: Some("Camera View Projection Buffer"),
-            contents: bytemuck::cast_slice(Mat4::IDENTITY.as_ref()),
+            size: MAX_VIEWPORT as BufferAddress * camera_uniform_aligned,
             usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
         });
         let light_uniform_size = size_of::<Light>() as BufferAddress;
         let light_buffer = device.create_buffer(&BufferDescriptor {
@@ -158,7 +165,11 @@ impl ShaderLit {
             entries: &[
                 BindGroupEntry {
                     binding: 0,
-                    resource: vp_buffer.as_entire_binding(),
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: &vp_buffer,
+                        offset: 0,
+                        size: BufferSize::new(camera_uniform_size),
+                    }),
                 },
                 BindGroupEntry {
                     binding: 1,
@@ -224,10 +235,18 @@ impl ShaderLit {
     }
 }
 impl Shader for ShaderLit {
-    fn set_pipeline<'a>(&'a self, pass: &mut RenderPass<'a>, offset: BufferAddress) {
+    fn kind(&self) -> ShaderKind {
+        ShaderKind::Lit
+    }
+    fn set_pipeline<'a>(
+        &'a self,
+        pass: &mut RenderPass<'a>,
+        offset: BufferAddress,
+        camera_offset: BufferAddress,
+    ) {
         let offsets = [offset as DynamicOffset, offset as DynamicOffset];
         pass.set_bind_group(0, &self.bind_group_node, &offsets);
-        pass.set_bind_group(1, &self.bind_group_camera, &[]);
+        pass.set_bind_group(1, &self.bind_group_camera, &[camera_offset as DynamicOffset]);
         pass.set_pipeline(&self.render_pipeline);
     }
     fn write_transform_data(&self, queue: &Queue, offset: BufferAddress, matrix: &[f32; 16]) {
@@ -239,8 +258,8 @@ impl Shader for ShaderLit {
     fn write_time_data(&self, _queue: &Queue, _time: f32) {
         // do nothing
     }
-    fn write_camera_data(&self, queue: &Queue, matrix: &[f32; 16]) {
-        queue.write_buffer(&self.vp_buffer, 0, bytemuck::bytes_of(matrix));
+    fn write_camera_data(&self, queue: &Queue, offset: BufferAddress, matrix: &[f32; 16]) {
+        queue.write_buffer(&self.vp_buffer, offset, bytemuck::bytes_of(matrix));
     }
     fn write_light_data(&self, queue: &Queue, lights: &[Light]) {
         let buffer = [lights.len() as u32, 0, 0, 0];
@@ -0,0 +1,220 @@
+use crate::rubik::{Rubik, Turn};
+
+/// Plays a move sequence on a `Rubik`, it can be paused, stepped and
+/// scrubbed to any point, including the middle of a turn.
+pub struct AlgorithmPlayer {
+    pub moves: Vec<Turn>,
+    /// Moves played so far, the fraction is how far into the next move we are
+    position: f32,
+    /// Moves committed to the cube
+    applied: usize,
+    pub playing: bool,
+    /// 1.0 plays every move at the speed set in the cube's animation settings
+    pub speed: f32,
+    /// Where a step is heading
+    target: Option<f32>,
+    /// Whether `rubik` was moving on its own before the player took it, set
+    /// while the player has it
+    auto_move: Option<bool>,
+}
+
+impl AlgorithmPlayer {
+    pub fn new() -> Self {
+        Self {
+            moves: Vec::new(),
+            position: 0.0,
+            applied: 0,
+            playing: false,
+            speed: 1.0,
+            target: None,
+            auto_move: None,
+        }
+    }
+    /// Take control of `rubik` and get ready to play `moves` from its current state
+    pub fn load(&mut self, moves: Vec<Turn>, rubik: &mut Rubik) {
+        // loading over another sequence keeps what was there before both
+        self.auto_move.get_or_insert(rubik.auto_move);
+        rubik.auto_move = false;
+        rubik.settle();
+        self.moves = moves;
+        self.position = 0.0;
+        self.applied = 0;
+        self.playing = false;
+        self.target = None;
+    }
+    /// Hand `rubik` back, turns left halfway go back to where they started
+    pub fn unload(&mut self, rubik: &mut Rubik) {
+        if rubik.is_turning() {
+            rubik.cancel_turns();
+        }
+        if let Some(auto_move) = self.auto_move.take() {
+            rubik.auto_move = auto_move;
+        }
+        self.moves = Vec::new();
+        self.position = 0.0;
+        self.applied = 0;
+        self.playing = false;
+        self.target = None;
+    }
+    pub fn is_loaded(&self) -> bool {
+        !self.moves.is_empty()
+    }
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+    pub fn step_forward(&mut self) {
+        self.playing = false;
+        self.target = Some((self.position.floor() + 1.0).min(self.len() as f32));
+    }
+    pub fn step_back(&mut self) {
+        self.playing = false;
+        self.target = Some((self.position.ceil() - 1.0).max(0.0));
+    }
+    /// Jump straight to `position` without animating, stopping any playback
+    pub fn scrub(&mut self, position: f32, rubik: &mut Rubik) {
+        self.playing = false;
+        self.target = None;
+        self.seek(position, rubik);
+    }
+    /// Bring `rubik` to `position` moves into the sequence
+    pub fn seek(&mut self, position: f32, rubik: &mut Rubik) {
+        let position = position.clamp(0.0, self.len() as f32);
+        let index = position.floor() as usize;
+        let fraction = position - index as f32;
+        if rubik.is_turning() && (fraction == 0.0 || index != self.applied) {
+            rubik.cancel_turns();
+        }
+        while self.applied < index {
+            rubik.apply_instant(self.moves[self.applied]);
+            self.applied += 1;
+        }
+        while self.applied > index {
+            self.applied -= 1;
+            rubik.apply_instant(self.moves[self.applied].inverse());
+        }
+        if fraction > 0.0 {
+            if !rubik.is_turning() {
+                rubik.start_turn(self.moves[index]);
+            }
+            rubik.seek(fraction * rubik.turn_duration());
+        }
+        self.position = position;
+    }
+    pub fn update(&mut self, delta_time: f32, rubik: &mut Rubik) {
+        if !self.is_loaded() {
+            return;
+        }
+        let index = (self.position.floor() as usize).min(self.len() - 1);
+        let duration = rubik
+            .animation
+            .duration_for(self.moves[index].quarter_turns);
+        let step = delta_time * self.speed / duration;
+        if let Some(target) = self.target {
+            let next = if target > self.position {
+                (self.position + step).min(target)
+            } else {
+                (self.position - step).max(target)
+            };
+            if next == target {
+                self.target = None;
+            }
+            self.seek(next, rubik);
+        } else if self.playing {
+            let next = (self.position + step).min(self.len() as f32);
+            if next == self.len() as f32 {
+                self.playing = false;
+            }
+            self.seek(next, rubik);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rubik::{PuzzleConfig, RubikState};
+
+    const ALGORITHM: &str = "R U R' U' 3Fw2 Lw' D";
+
+    fn same_state(a: &RubikState, b: &RubikState) -> bool {
+        a.cubies.iter().zip(b.cubies.iter()).all(|(a, b)| {
+            a.position == b.position
+                && a.stickers.iter().zip(b.stickers.iter()).all(|(a, b)| a.normal == b.normal)
+        })
+    }
+
+    fn loaded() -> (AlgorithmPlayer, Rubik) {
+        let mut rubik = Rubik::headless(PuzzleConfig::new(2));
+        let mut player = AlgorithmPlayer::new();
+        player.load(Turn::parse_sequence(ALGORITHM, 2).unwrap(), &mut rubik);
+        (player, rubik)
+    }
+
+    /// The cube once the first `count` moves of the algorithm are applied
+    fn played(count: usize) -> RubikState {
+        let mut state = RubikState::new(&PuzzleConfig::new(2));
+        for turn in Turn::parse_sequence(ALGORITHM, 2).unwrap().into_iter().take(count) {
+            state.apply(turn);
+        }
+        state
+    }
+
+    #[test]
+    fn seek_matches_applying_the_moves() {
+        let (mut player, mut rubik) = loaded();
+        player.seek(player.len() as f32, &mut rubik);
+        assert!(same_state(&rubik.state, &played(7)));
+        player.seek(3.0, &mut rubik);
+        assert!(same_state(&rubik.state, &played(3)));
+        player.seek(0.0, &mut rubik);
+        assert!(rubik.state.is_solved());
+        assert!(!rubik.is_turning());
+    }
+
+    #[test]
+    fn seek_halfway_keeps_the_move_in_flight() {
+        let (mut player, mut rubik) = loaded();
+        player.seek(2.5, &mut rubik);
+        assert!(rubik.is_turning());
+        assert!(same_state(&rubik.state, &played(2)));
+        player.seek(1.5, &mut rubik);
+        assert!(same_state(&rubik.state, &played(1)));
+        player.seek(3.0, &mut rubik);
+        assert!(!rubik.is_turning());
+        assert!(same_state(&rubik.state, &played(3)));
+    }
+
+    #[test]
+    fn steps_play_one_move_at_a_time() {
+        let (mut player, mut rubik) = loaded();
+        for count in 1..=player.len() {
+            player.step_forward();
+            while player.target.is_some() {
+                player.update(0.1, &mut rubik);
+            }
+            assert_eq!(player.position(), count as f32);
+            assert!(same_state(&rubik.state, &played(count)));
+        }
+        player.step_forward();
+        player.update(0.1, &mut rubik);
+        assert_eq!(player.position(), player.len() as f32);
+        player.step_back();
+        while player.target.is_some() {
+            player.update(0.1, &mut rubik);
+        }
+        assert!(same_state(&rubik.state, &played(player.len() - 1)));
+    }
+
+    #[test]
+    fn unload_gives_auto_move_back() {
+        let (mut player, mut rubik) = loaded();
+        assert!(!rubik.auto_move);
+        player.load(Turn::parse_sequence("R", 2).unwrap(), &mut rubik);
+        player.unload(&mut rubik);
+        assert!(rubik.auto_move);
+        assert!(!player.is_loaded());
+    }
+}
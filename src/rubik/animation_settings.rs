@@ -0,0 +1,37 @@
+use crate::world::Easing;
+use rand::Rng;
+
+/// How turns are animated, shared by manual and automatic moves
+#[derive(Clone)]
+pub struct AnimationSettings {
+    /// `None` picks a different easing for every turn
+    pub easing: Option<Easing>,
+    /// Seconds a quarter turn takes
+    pub duration: f32,
+    /// Make half turns take twice as long as quarter turns and so on
+    pub scale_by_turns: bool,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self {
+            easing: Some(Easing::CubicInOut),
+            duration: 0.5,
+            scale_by_turns: true,
+        }
+    }
+}
+
+impl AnimationSettings {
+    pub fn pick_easing<R: Rng>(&self, rng: &mut R) -> Easing {
+        self.easing
+            .unwrap_or_else(|| Easing::ALL[rng.random_range(0..Easing::ALL.len() as u32) as usize])
+    }
+    pub fn duration_for(&self, quarter_turns: i32) -> f32 {
+        if self.scale_by_turns {
+            self.duration * quarter_turns.unsigned_abs().max(1) as f32
+        } else {
+            self.duration
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use crate::rubik::{AlgorithmPlayer, Rubik, Turn};
+use crate::world::{Camera, Clip, Easing, Track};
+use glam::Vec3;
+use rand::Rng;
+
+/// What the attract loop is showing
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AttractPhase {
+    /// Undoing every turn since the cube was last solved, or putting it
+    /// back at once when the history no longer gets there
+    Solve,
+    /// Holding the solved cube
+    Solved,
+    Scramble,
+    /// Holding the scrambled cube
+    Scrambled,
+}
+
+#[derive(Clone)]
+pub struct AttractSettings {
+    /// Start on its own once nobody has touched anything for `idle_time`
+    pub auto_start: bool,
+    /// Seconds without input before starting
+    pub idle_time: f32,
+    pub scramble_length: usize,
+    /// Playback speeds, 1.0 plays turns at the cube's animation duration
+    pub scramble_speed: f32,
+    pub solve_speed: f32,
+    /// Seconds to hold the cube between scrambling and solving
+    pub pause: f32,
+    /// Fly the camera to a new view between phases
+    pub camera_moves: bool,
+    /// Seconds a camera move takes
+    pub camera_duration: f32,
+}
+
+impl Default for AttractSettings {
+    fn default() -> Self {
+        Self {
+            auto_start: true,
+            idle_time: 60.0,
+            scramble_length: 20,
+            scramble_speed: 3.0,
+            solve_speed: 1.5,
+            pause: 2.0,
+            camera_moves: true,
+            camera_duration: 1.5,
+        }
+    }
+}
+
+/// Loops the cube through scrambling, a pause and solving back, for when
+/// nobody is using the app. Stops as soon as somebody does.
+pub struct AttractMode {
+    pub settings: AttractSettings,
+    phase: Option<AttractPhase>,
+    /// Seconds since the last input
+    idle: f32,
+    /// Seconds spent holding the cube in a pause phase
+    timer: f32,
+    player: AlgorithmPlayer,
+    /// Whether the cube was moving on its own before we took over
+    auto_move: bool,
+    /// Camera distance, azimuth and elevation over time
+    camera_move: Option<Clip<Vec3>>,
+}
+
+impl AttractMode {
+    pub fn new() -> Self {
+        Self {
+            settings: AttractSettings::default(),
+            phase: None,
+            idle: 0.0,
+            timer: 0.0,
+            player: AlgorithmPlayer::new(),
+            auto_move: false,
+            camera_move: None,
+        }
+    }
+    pub fn is_active(&self) -> bool {
+        self.phase.is_some()
+    }
+    pub fn phase(&self) -> Option<AttractPhase> {
+        self.phase
+    }
+    /// Take over `rubik`, starting by solving it back
+    pub fn start(&mut self, rubik: &mut Rubik, camera: &mut Camera) {
+        if self.is_active() {
+            return;
+        }
+        self.auto_move = rubik.auto_move;
+        self.enter(AttractPhase::Solve, rubik, camera);
+    }
+    /// Hand `rubik` back where it is, a turn left halfway goes back
+    pub fn stop(&mut self, rubik: &mut Rubik) {
+        if !self.is_active() {
+            return;
+        }
+        self.player.unload(rubik);
+        rubik.auto_move = self.auto_move;
+        self.phase = None;
+        self.camera_move = None;
+    }
+    /// Somebody is using the app: stop and start counting idle time again
+    pub fn input(&mut self, rubik: &mut Rubik) {
+        self.idle = 0.0;
+        self.stop(rubik);
+    }
+    fn enter(&mut self, phase: AttractPhase, rubik: &mut Rubik, camera: &mut Camera) {
+        self.phase = Some(phase);
+        self.timer = 0.0;
+        match phase {
+            AttractPhase::Solve => {
+                if !rubik.history_complete() {
+                    rubik.reset();
+                }
+                let moves = rubik.history().iter().rev().map(Turn::inverse).collect();
+                self.play(moves, self.settings.solve_speed, rubik);
+            }
+            AttractPhase::Scramble => {
+                let mut moves: Vec<Turn> = Vec::new();
+                while moves.len() < self.settings.scramble_length {
+                    let turn = rubik.random_turn();
+                    // two turns in a row on the same axis would waste a move
+                    let same_axis = moves.last().is_some_and(|last| {
+                        last.face.axis().abs() == turn.face.axis().abs()
+                    });
+                    if !same_axis {
+                        moves.push(turn);
+                    }
+                }
+                self.play(moves, self.settings.scramble_speed, rubik);
+            }
+            AttractPhase::Solved | AttractPhase::Scrambled => {}
+        }
+        if self.settings.camera_moves {
+            self.move_camera(camera, rubik);
+        }
+    }
+    fn play(&mut self, moves: Vec<Turn>, speed: f32, rubik: &mut Rubik) {
+        self.player.load(moves, rubik);
+        self.player.speed = speed;
+        self.player.playing = true;
+    }
+    /// Start flying the camera to a random view around the cube, picked
+    /// with the cube's seed so a run replays the same views
+    fn move_camera(&mut self, camera: &Camera, rubik: &mut Rubik) {
+        let rng = rubik.rng();
+        let from = Vec3::new(camera.distance, camera.azimuth, camera.elevation);
+        let turn = rng.random_range(0.6..1.6) * if rng.random_bool(0.5) { 1.0 } else { -1.0 };
+        let to = Vec3::new(
+            camera.framing_distance() * rng.random_range(0.85..1.4),
+            camera.azimuth + turn,
+            rng.random_range(-0.3..0.9),
+        );
+        let duration = self.settings.camera_duration.max(0.001);
+        self.camera_move = Some(Clip::new(Track::tween(from, to, duration, Easing::SineInOut)));
+    }
+    fn phase_done(&self) -> bool {
+        match self.phase {
+            Some(AttractPhase::Solve | AttractPhase::Scramble) => {
+                !self.player.is_loaded() || self.player.position() >= self.player.len() as f32
+            }
+            Some(AttractPhase::Solved | AttractPhase::Scrambled) => {
+                self.timer >= self.settings.pause
+            }
+            None => false,
+        }
+    }
+    /// Count idle time, start when it's time to, and drive `rubik` and the
+    /// camera while active
+    pub fn update(&mut self, delta_time: f32, rubik: &mut Rubik, camera: &mut Camera) {
+        self.idle += delta_time;
+        if !self.is_active() {
+            if self.settings.auto_start && self.idle >= self.settings.idle_time {
+                self.start(rubik, camera);
+            }
+            return;
+        }
+        self.timer += delta_time;
+        self.player.update(delta_time, rubik);
+        if let Some(clip) = self.camera_move.as_mut() {
+            let pose = clip.move_by(delta_time);
+            camera.set_orbit(pose.x, pose.y, pose.z);
+            if clip.is_finished() {
+                self.camera_move = None;
+            }
+        }
+        if self.phase_done() {
+            let next = match self.phase {
+                Some(AttractPhase::Solve) => AttractPhase::Solved,
+                Some(AttractPhase::Solved) => AttractPhase::Scramble,
+                Some(AttractPhase::Scramble) => AttractPhase::Scrambled,
+                _ => AttractPhase::Solve,
+            };
+            self.enter(next, rubik, camera);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rubik::{Move, PuzzleConfig};
+
+    fn idle_cube() -> (AttractMode, Rubik, Camera) {
+        let mut attract = AttractMode::new();
+        attract.settings.idle_time = 1.0;
+        attract.settings.scramble_length = 6;
+        (attract, Rubik::headless(PuzzleConfig::new(1)), Camera::new())
+    }
+
+    /// Run until the phase changes, returning the new one
+    fn next_phase(attract: &mut AttractMode, rubik: &mut Rubik, camera: &mut Camera) -> AttractPhase {
+        let phase = attract.phase();
+        for _ in 0..1000 {
+            attract.update(0.05, rubik, camera);
+            if attract.phase() != phase {
+                return attract.phase().unwrap();
+            }
+        }
+        panic!("stuck in {phase:?}");
+    }
+
+    #[test]
+    fn loops_through_the_phases_once_idle() {
+        let (mut attract, mut rubik, mut camera) = idle_cube();
+        rubik.apply_instant(Turn::new(Move::Right, 1, 1));
+        attract.update(0.6, &mut rubik, &mut camera);
+        assert!(!attract.is_active());
+        attract.update(0.6, &mut rubik, &mut camera);
+        assert_eq!(attract.phase(), Some(AttractPhase::Solve));
+        let expected = [
+            AttractPhase::Solved,
+            AttractPhase::Scramble,
+            AttractPhase::Scrambled,
+            AttractPhase::Solve,
+            AttractPhase::Solved,
+        ];
+        for phase in expected {
+            assert_eq!(next_phase(&mut attract, &mut rubik, &mut camera), phase);
+            match phase {
+                AttractPhase::Solved => assert!(rubik.state.is_solved()),
+                AttractPhase::Scrambled => assert!(!rubik.state.is_solved()),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn input_stops_it_and_hands_the_cube_back() {
+        let (mut attract, mut rubik, mut camera) = idle_cube();
+        attract.start(&mut rubik, &mut camera);
+        next_phase(&mut attract, &mut rubik, &mut camera);
+        next_phase(&mut attract, &mut rubik, &mut camera);
+        assert_eq!(attract.phase(), Some(AttractPhase::Scramble));
+        assert!(!rubik.auto_move);
+        attract.input(&mut rubik);
+        assert!(!attract.is_active());
+        assert!(rubik.auto_move);
+        // idle time counts from the input again
+        attract.update(0.6, &mut rubik, &mut camera);
+        assert!(!attract.is_active());
+    }
+
+    #[test]
+    fn a_lost_history_is_solved_at_once() {
+        let (mut attract, mut rubik, mut camera) = idle_cube();
+        rubik.reseed(1);
+        for _ in 0..400 {
+            let turn = rubik.random_turn();
+            rubik.apply_instant(turn);
+        }
+        assert!(!rubik.history_complete());
+        attract.start(&mut rubik, &mut camera);
+        assert!(rubik.state.is_solved());
+        assert_eq!(next_phase(&mut attract, &mut rubik, &mut camera), AttractPhase::Solved);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::rubik::{Move, Rubik, Turn};
+use crate::world::{Node, Renderer, raycast};
+use glam::{IVec3, Vec2, Vec3};
+
+/// Pixels the mouse has to travel before we decide which layer it turns
+const DRAG_THRESHOLD: f32 = 10.0;
+
+/// The layer a drag settled on and how the mouse maps to its angle
+struct Grip {
+    /// Screen direction the sticker moves in when the layer turns clockwise
+    clockwise: Vec2,
+    /// Pixels the sticker travels per radian
+    pixels_per_radian: f32,
+}
+
+struct Grab {
+    /// Cursor position when the button went down
+    start: Vec2,
+    /// Where the sticker was hit, in the cube's own space
+    point: Vec3,
+    /// Face the sticker is on, in the cube's own space
+    normal: IVec3,
+    /// Position of the piece in the cube
+    position: IVec3,
+    grip: Option<Grip>,
+}
+
+/// Turns a layer by pressing on one of its stickers and dragging along the
+/// face. The layer follows the mouse and snaps to the nearest quarter turn
+/// once let go.
+pub struct DragTurn {
+    grab: Option<Grab>,
+}
+
+impl DragTurn {
+    pub fn new() -> Self {
+        Self { grab: None }
+    }
+    /// A sticker is held, the cube shouldn't move on its own meanwhile
+    pub fn is_active(&self) -> bool {
+        self.grab.is_some()
+    }
+    /// Position of the piece held and the way its sticker faces
+    pub fn sticker(&self) -> Option<(IVec3, IVec3)> {
+        self.grab.as_ref().map(|grab| (grab.position, grab.normal))
+    }
+    /// Take hold of the sticker under the cursor at `x`, `y`. Returns false if
+    /// there isn't one, so the press can do something else.
+    pub fn press(&mut self, x: f32, y: f32, rubik: &mut Rubik, renderer: &Renderer) -> bool {
+        let ray = renderer.ray(x, y);
+        let layers = renderer.camera.layers;
+        let on_cube = raycast(&rubik.root, &ray, layers)
+            .is_some_and(|hit| rubik.piece_index(&hit.node).is_some());
+        if !on_cube {
+            return false;
+        }
+        // pieces in flight would move from under the cursor, finish them first
+        rubik.settle();
+        let Some((index, point, face)) = rubik.sticker_hit(&ray, layers) else {
+            return false;
+        };
+        let position = rubik.state.cubies[index].position;
+        self.grab = Some(Grab {
+            start: Vec2::new(x, y),
+            point,
+            normal: face,
+            position,
+            grip: None,
+        });
+        true
+    }
+    /// Follow the cursor at `x`, `y`, picking the layer once it's clear
+    /// which way the mouse is going
+    pub fn drag(&mut self, x: f32, y: f32, rubik: &mut Rubik, renderer: &Renderer) {
+        let Some(grab) = self.grab.as_mut() else {
+            return;
+        };
+        let moved = Vec2::new(x, y) - grab.start;
+        if grab.grip.is_none() {
+            if moved.length() < DRAG_THRESHOLD {
+                return;
+            }
+            grab.grip = Self::pick_layer(grab, moved, rubik, renderer);
+        }
+        if let Some(grip) = &grab.grip {
+            rubik.drag_turn(moved.dot(grip.clockwise) / grip.pixels_per_radian);
+        }
+    }
+    /// Let go, the layer snaps to the nearest quarter turn
+    pub fn release(&mut self, rubik: &mut Rubik) {
+        if self.grab.take().is_some_and(|grab| grab.grip.is_some()) {
+            rubik.release_turn();
+        }
+    }
+    /// Of the two layers through the sticker, grab the one whose turn moves
+    /// the sticker closest to the way the mouse went
+    fn pick_layer(grab: &Grab, moved: Vec2, rubik: &mut Rubik, renderer: &Renderer) -> Option<Grip> {
+        let world = rubik.root.world_transform();
+        let origin = world.transform_point3(grab.point);
+        let start = renderer.project(origin);
+        let normal = grab.normal.as_vec3();
+        let (turn, clockwise) = [Move::Right, Move::Front, Move::Top]
+            .into_iter()
+            .filter(|face| face.axis().dot(grab.normal) == 0)
+            .map(|face| {
+                let axis = face.axis().as_vec3();
+                // a clockwise turn seen from the face is a negative rotation
+                // around its axis
+                let motion = world.transform_vector3(normal.cross(axis));
+                let clockwise = renderer.project(origin + motion) - start;
+                let coordinate = grab.position.dot(face.axis());
+                let span = rubik.state.span as i32;
+                let turn = Turn::slice(face, (span - coordinate) as usize, 1, 1);
+                (turn, clockwise)
+            })
+            .max_by(|(_, a), (_, b)| {
+                let along = |v: &Vec2| moved.dot(v.normalize_or_zero()).abs();
+                along(a).total_cmp(&along(b))
+            })?;
+        // the sticker is this far from the axis, and moves this far per radian
+        let radius = grab.point.dot(normal);
+        let pixels_per_radian = clockwise.length() * radius;
+        if pixels_per_radian <= 0.0 || !rubik.grab_turn(turn) {
+            return None;
+        }
+        Some(Grip {
+            clockwise: clockwise.normalize(),
+            pixels_per_radian,
+        })
+    }
+}
@@ -0,0 +1,289 @@
+use crate::rubik::Turn;
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use winit::keyboard::KeyCode;
+
+/// Name the keymap is saved under
+const SETTINGS_NAME: &str = "keymap";
+
+/// Starting points for the key bindings
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum KeymapPreset {
+    /// Both hands on the home row, as in csTimer and qqTimer
+    CsTimer,
+    /// The key of each face letter, Shift for the inverse
+    Singmaster,
+    /// Edited by hand
+    Custom,
+}
+
+impl KeymapPreset {
+    pub const ALL: [KeymapPreset; 3] = [
+        KeymapPreset::CsTimer,
+        KeymapPreset::Singmaster,
+        KeymapPreset::Custom,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeymapPreset::CsTimer => "csTimer",
+            KeymapPreset::Singmaster => "Singmaster",
+            KeymapPreset::Custom => "Custom",
+        }
+    }
+}
+
+/// Keys the app keeps for itself: Space restarts the animation, P pauses it
+/// and Escape quits. Bindings can't take them.
+pub const RESERVED_KEYS: [KeyCode; 3] = [KeyCode::Space, KeyCode::KeyP, KeyCode::Escape];
+
+/// Keys only ever held together with another one
+pub fn is_modifier(key: KeyCode) -> bool {
+    matches!(
+        key,
+        KeyCode::ShiftLeft
+            | KeyCode::ShiftRight
+            | KeyCode::ControlLeft
+            | KeyCode::ControlRight
+            | KeyCode::AltLeft
+            | KeyCode::AltRight
+            | KeyCode::SuperLeft
+            | KeyCode::SuperRight
+            | KeyCode::Meta
+            | KeyCode::Hyper
+            | KeyCode::Fn
+    )
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct KeyBinding {
+    /// None until a key is picked for it
+    pub key: Option<KeyCode>,
+    pub shift: bool,
+    /// Move in standard notation, see `Turn::parse_on`
+    pub notation: String,
+}
+
+impl KeyBinding {
+    pub fn new(key: KeyCode, shift: bool, notation: &str) -> Self {
+        Self {
+            key: Some(key),
+            shift,
+            notation: notation.to_string(),
+        }
+    }
+    /// A binding with no key yet
+    pub fn unbound(notation: &str) -> Self {
+        Self {
+            key: None,
+            shift: false,
+            notation: notation.to_string(),
+        }
+    }
+    /// Short name of the key, like `R`, `Shift+R` or `Semicolon`
+    pub fn key_name(&self) -> String {
+        let Some(key) = self.key else {
+            return String::from("Unbound");
+        };
+        let name = format!("{key:?}");
+        let name = name
+            .strip_prefix("Key")
+            .or_else(|| name.strip_prefix("Digit"))
+            .unwrap_or(&name)
+            .to_string();
+        if self.shift { format!("Shift+{name}") } else { name }
+    }
+}
+
+const CSTIMER: [(KeyCode, &str); 28] = [
+    (KeyCode::KeyI, "R"),
+    (KeyCode::KeyK, "R'"),
+    (KeyCode::KeyW, "B"),
+    (KeyCode::KeyO, "B'"),
+    (KeyCode::KeyS, "D"),
+    (KeyCode::KeyL, "D'"),
+    (KeyCode::KeyD, "L"),
+    (KeyCode::KeyE, "L'"),
+    (KeyCode::KeyJ, "U"),
+    (KeyCode::KeyF, "U'"),
+    (KeyCode::KeyH, "F"),
+    (KeyCode::KeyG, "F'"),
+    (KeyCode::Semicolon, "y"),
+    (KeyCode::KeyA, "y'"),
+    (KeyCode::KeyU, "r"),
+    (KeyCode::KeyM, "r'"),
+    (KeyCode::KeyV, "l"),
+    (KeyCode::KeyR, "l'"),
+    (KeyCode::KeyT, "x"),
+    (KeyCode::KeyY, "x"),
+    (KeyCode::KeyB, "x'"),
+    (KeyCode::KeyN, "x'"),
+    // csTimer has z on P, which pauses here
+    (KeyCode::KeyZ, "z"),
+    (KeyCode::KeyQ, "z'"),
+    (KeyCode::Digit5, "M"),
+    (KeyCode::Digit6, "M"),
+    (KeyCode::KeyX, "M'"),
+    (KeyCode::Period, "M'"),
+];
+
+const SINGMASTER: [(KeyCode, &str); 12] = [
+    (KeyCode::KeyU, "U"),
+    (KeyCode::KeyD, "D"),
+    (KeyCode::KeyL, "L"),
+    (KeyCode::KeyR, "R"),
+    (KeyCode::KeyF, "F"),
+    (KeyCode::KeyB, "B"),
+    (KeyCode::KeyM, "M"),
+    (KeyCode::KeyE, "E"),
+    (KeyCode::KeyS, "S"),
+    (KeyCode::KeyX, "x"),
+    (KeyCode::KeyY, "y"),
+    (KeyCode::KeyZ, "z"),
+];
+
+/// Which keys turn which layers
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Keymap {
+    pub enabled: bool,
+    pub preset: KeymapPreset,
+    pub bindings: Vec<KeyBinding>,
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        let mut keymap = Self {
+            enabled: true,
+            preset: KeymapPreset::Singmaster,
+            bindings: Vec::new(),
+        };
+        keymap.load_preset(KeymapPreset::Singmaster);
+        keymap
+    }
+    /// The keymap kept by `save`, or the default one when there is none or
+    /// it can't be read
+    pub fn load() -> Self {
+        let Some(text) = settings::load(SETTINGS_NAME) else {
+            return Self::new();
+        };
+        Self::from_ron(&text).unwrap_or_else(|e| {
+            log::error!("failed to read the saved keymap: {e}");
+            Self::new()
+        })
+    }
+    /// Keep the keymap for the next run
+    pub fn save(&self) {
+        match self.to_ron() {
+            Ok(text) => settings::save(SETTINGS_NAME, &text),
+            Err(e) => log::error!("failed to write the keymap: {e}"),
+        }
+    }
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+    }
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        ron::from_str(text).map_err(|e| e.to_string())
+    }
+    /// Replace the bindings with those of `preset`, `Custom` keeps them as they are
+    pub fn load_preset(&mut self, preset: KeymapPreset) {
+        self.preset = preset;
+        match preset {
+            KeymapPreset::CsTimer => {
+                self.bindings = CSTIMER
+                    .iter()
+                    .map(|(key, notation)| KeyBinding::new(*key, false, notation))
+                    .collect();
+            }
+            KeymapPreset::Singmaster => {
+                self.bindings = SINGMASTER
+                    .iter()
+                    .flat_map(|(key, notation)| {
+                        [
+                            KeyBinding::new(*key, false, notation),
+                            KeyBinding::new(*key, true, &format!("{notation}'")),
+                        ]
+                    })
+                    .collect();
+            }
+            KeymapPreset::Custom => {}
+        }
+    }
+    /// Point `key` at a new move, the bindings are custom from then on.
+    /// Reserved keys and modifiers on their own are refused.
+    pub fn bind(&mut self, index: usize, key: KeyCode, shift: bool) -> bool {
+        if RESERVED_KEYS.contains(&key) || is_modifier(key) {
+            return false;
+        }
+        if let Some(binding) = self.bindings.get_mut(index) {
+            binding.key = Some(key);
+            binding.shift = shift;
+            self.preset = KeymapPreset::Custom;
+        }
+        true
+    }
+    /// Turn bound to `key` on a cube of the given span. Reserved keys keep
+    /// their own job whatever they are bound to.
+    pub fn turn(&self, key: KeyCode, shift: bool, span: usize) -> Option<Turn> {
+        if !self.enabled || RESERVED_KEYS.contains(&key) {
+            return None;
+        }
+        let binding = self
+            .bindings
+            .iter()
+            .find(|binding| binding.key == Some(key) && binding.shift == shift)?;
+        Turn::parse_on(&binding.notation, span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_leave_reserved_keys_alone() {
+        for preset in [KeymapPreset::CsTimer, KeymapPreset::Singmaster] {
+            let mut keymap = Keymap::new();
+            keymap.load_preset(preset);
+            for binding in keymap.bindings.iter() {
+                assert!(binding.key.is_some_and(|key| !RESERVED_KEYS.contains(&key)));
+            }
+        }
+    }
+
+    #[test]
+    fn reserved_keys_never_turn() {
+        let mut keymap = Keymap::new();
+        keymap.bindings.push(KeyBinding::new(KeyCode::KeyP, false, "z"));
+        keymap.bindings.push(KeyBinding::new(KeyCode::Space, false, "U"));
+        assert!(keymap.turn(KeyCode::KeyP, false, 1).is_none());
+        assert!(keymap.turn(KeyCode::Space, false, 1).is_none());
+        assert!(keymap.turn(KeyCode::KeyR, false, 1).is_some());
+    }
+
+    #[test]
+    fn bind_refuses_reserved_keys_and_modifiers() {
+        let mut keymap = Keymap::new();
+        keymap.bindings.push(KeyBinding::unbound("U"));
+        let index = keymap.bindings.len() - 1;
+        assert_eq!(keymap.bindings[index].key_name(), "Unbound");
+        for key in [KeyCode::Space, KeyCode::KeyP, KeyCode::ShiftLeft, KeyCode::ControlRight] {
+            assert!(!keymap.bind(index, key, false));
+        }
+        assert!(keymap.bindings[index].key.is_none());
+        assert!(keymap.bind(index, KeyCode::KeyQ, true));
+        assert_eq!(keymap.bindings[index].key_name(), "Shift+Q");
+        assert_eq!(keymap.preset, KeymapPreset::Custom);
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let mut keymap = Keymap::new();
+        keymap.load_preset(KeymapPreset::CsTimer);
+        keymap.enabled = false;
+        keymap.bindings.push(KeyBinding::unbound("3Rw2"));
+        assert!(keymap.bind(0, KeyCode::Semicolon, true));
+        let text = keymap.to_ron().unwrap();
+        assert_eq!(Keymap::from_ron(&text).unwrap(), keymap);
+        assert!(Keymap::from_ron("(enabled: true)").is_err());
+    }
+}
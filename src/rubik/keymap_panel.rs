@@ -0,0 +1,61 @@
+use crate::rubik::{KeyBinding, Keymap, KeymapPreset};
+
+/// Edit `keymap`, saving it whenever it changes. Clicking a key puts its
+/// binding in `rebinding` until the app gets the new key.
+pub fn keymap_panel(ui: &mut egui::Ui, keymap: &mut Keymap, rebinding: &mut Option<usize>) {
+    let before = keymap.clone();
+    ui.checkbox(&mut keymap.enabled, "Turn layers with the keyboard");
+    egui::ComboBox::from_label("Layout")
+        .selected_text(keymap.preset.name())
+        .show_ui(ui, |ui| {
+            for preset in KeymapPreset::ALL {
+                if ui
+                    .selectable_label(keymap.preset == preset, preset.name())
+                    .clicked()
+                {
+                    keymap.load_preset(preset);
+                }
+            }
+        });
+    ui.label("Click a key to change it, then press the new key");
+    ui.label("Space, P and Escape keep their own jobs and can't be bound");
+    let mut removed = None;
+    egui::ScrollArea::vertical()
+        .max_height(300.0)
+        .show(ui, |ui| {
+            egui::Grid::new("bindings").show(ui, |ui| {
+                for (i, binding) in keymap.bindings.iter_mut().enumerate() {
+                    let label = if *rebinding == Some(i) {
+                        String::from("Press a key...")
+                    } else {
+                        binding.key_name()
+                    };
+                    if ui.button(label).clicked() {
+                        *rebinding = Some(i);
+                    }
+                    let notation = ui
+                        .add(egui::TextEdit::singleline(&mut binding.notation).desired_width(60.0));
+                    if notation.changed() {
+                        keymap.preset = KeymapPreset::Custom;
+                    }
+                    if ui.button("Remove").clicked() {
+                        removed = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    if let Some(i) = removed {
+        keymap.bindings.remove(i);
+        keymap.preset = KeymapPreset::Custom;
+        *rebinding = None;
+    }
+    if ui.button("Add binding").clicked() {
+        keymap.bindings.push(KeyBinding::unbound("U"));
+        keymap.preset = KeymapPreset::Custom;
+        *rebinding = Some(keymap.bindings.len() - 1);
+    }
+    if *keymap != before {
+        keymap.save();
+    }
+}
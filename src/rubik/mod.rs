@@ -1,4 +1,28 @@
+pub mod algorithm_player;
+pub mod animation_settings;
+pub mod attract_mode;
+pub mod drag_turn;
+pub mod keymap;
+pub mod keymap_panel;
+pub mod net_panel;
+pub mod paint_panel;
+pub mod puzzle_config;
 pub mod rubik_cube;
 pub mod rubik_move;
-pub use rubik_cube::Rubik;
-pub use rubik_move::Move;
+pub mod rubik_net;
+pub mod rubik_state;
+pub mod sticker_paint;
+pub use algorithm_player::AlgorithmPlayer;
+pub use animation_settings::AnimationSettings;
+pub use attract_mode::AttractMode;
+pub use drag_turn::DragTurn;
+pub use keymap::{KeyBinding, Keymap, KeymapPreset};
+pub use keymap_panel::keymap_panel;
+pub use net_panel::net_panel;
+pub use paint_panel::{paint_panel, repaint_cube};
+pub use puzzle_config::PuzzleConfig;
+pub use rubik_cube::{LAYER_CENTERS, LAYER_CORE, LAYER_CORNERS, LAYER_EDGES, Rubik};
+pub use rubik_move::{Move, Turn};
+pub use rubik_net::{Facelet, RubikNet};
+pub use rubik_state::RubikState;
+pub use sticker_paint::StickerPaint;
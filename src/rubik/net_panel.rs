@@ -0,0 +1,87 @@
+use crate::rubik::paint_panel::repaint_cube;
+use crate::rubik::rubik_net::{EMPTY_COLOR, net_size};
+use crate::rubik::{Facelet, Rubik, RubikNet, StickerPaint};
+use crate::world::Renderer;
+use glam::Vec2;
+
+/// An RGBA colour as egui takes it
+pub fn color32(color: u32) -> egui::Color32 {
+    let [r, g, b, a] = color.to_be_bytes();
+    egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+}
+
+/// Draw the net of `rubik`, or of what is painted so far, and select the
+/// clicked facelet, painting it too while `paint` is on
+pub fn net_panel(
+    ui: &mut egui::Ui,
+    net: &mut RubikNet,
+    rubik: &mut Rubik,
+    paint: Option<&mut StickerPaint>,
+    renderer: &Renderer,
+) {
+    let span = rubik.state.span;
+    let size = net_size(span);
+    let scale = ui.available_width().max(240.0) / size.x;
+    let (response, painter) =
+        ui.allocate_painter(egui::vec2(size.x, size.y) * scale, egui::Sense::click());
+    let origin = response.rect.min;
+    for facelet in Facelet::all(span) {
+        let corner = facelet.corner(span);
+        let rect = egui::Rect::from_min_size(
+            origin + egui::vec2(corner.x, corner.y) * scale,
+            egui::vec2(scale, scale),
+        )
+        .shrink(scale * 0.05);
+        let color = match &paint {
+            Some(paint) => paint.color(&facelet),
+            None => facelet.color(rubik).unwrap_or(EMPTY_COLOR),
+        };
+        painter.rect_filled(rect, scale * 0.1, color32(color));
+        if net.selected == Some(facelet) {
+            painter.rect_stroke(
+                rect,
+                scale * 0.1,
+                egui::Stroke::new(2.0, egui::Color32::WHITE),
+                egui::StrokeKind::Outside,
+            );
+        }
+        if facelet.row == span && facelet.column == span {
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                facelet.face.letter(),
+                egui::FontId::proportional(scale * 0.6),
+                egui::Color32::BLACK,
+            );
+        }
+    }
+    if response.clicked()
+        && let Some(pointer) = response.interact_pointer_pos()
+    {
+        let p = (pointer - origin) / scale;
+        net.selected = Facelet::at(Vec2::new(p.x, p.y), span);
+        if let Some(paint) = paint
+            && let Some(facelet) = net.selected
+            && paint.paint(&facelet)
+        {
+            repaint_cube(rubik, paint, renderer);
+        }
+    }
+    match net.selected {
+        Some(facelet) => {
+            let p = facelet.position(span);
+            ui.label(format!(
+                "Selected {} row {} column {}, on the piece at ({}, {}, {})",
+                facelet.face.letter(),
+                facelet.row + 1,
+                facelet.column + 1,
+                p.x,
+                p.y,
+                p.z
+            ));
+        }
+        None => {
+            ui.label("Click a sticker to select it");
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::geometry::rubik_piece::{FACE_COLOR_NAMES, PIECE_COLOR};
+use crate::rubik::net_panel::color32;
+use crate::rubik::rubik_net::{EMPTY_COLOR, face_color};
+use crate::rubik::rubik_state::FACE_NORMALS;
+use crate::rubik::{AttractMode, Facelet, Rubik, StickerPaint};
+use crate::world::Renderer;
+
+/// Show the colours of `paint` on the pieces of `rubik`
+pub fn repaint_cube(rubik: &mut Rubik, paint: &StickerPaint, renderer: &Renderer) {
+    let span = rubik.state.span;
+    rubik.repaint(renderer, |position, normal| {
+        Facelet::from_sticker(position, normal, span)
+            .map_or(EMPTY_COLOR, |facelet| paint.color(&facelet))
+    });
+}
+
+/// Start painting `rubik` when `can_paint`, then pick colours, check what
+/// is painted and load it or give up. `status` tells how the last of these
+/// went.
+pub fn paint_panel(
+    ui: &mut egui::Ui,
+    paint: &mut Option<StickerPaint>,
+    status: &mut Option<String>,
+    rubik: &mut Rubik,
+    attract: &mut AttractMode,
+    renderer: &Renderer,
+    can_paint: bool,
+) {
+    let Some(painting) = paint.as_mut() else {
+        ui.label("Paint the stickers after a real cube to load its state");
+        if ui
+            .add_enabled(can_paint, egui::Button::new("Start painting"))
+            .clicked()
+        {
+            attract.stop(rubik);
+            rubik.settle();
+            match StickerPaint::new(rubik) {
+                Ok(painting) => {
+                    rubik.auto_move = false;
+                    repaint_cube(rubik, &painting, renderer);
+                    *paint = Some(painting);
+                    *status = None;
+                }
+                Err(e) => *status = Some(e),
+            }
+        }
+        if let Some(status) = status {
+            ui.label(status.as_str());
+        }
+        return;
+    };
+    ui.label("Pick a colour, then click the stickers on the cube or the net");
+    ui.label("The app can't solve a loaded cube yet, that is up to you");
+    ui.horizontal(|ui| {
+        for (normal, name) in FACE_NORMALS.iter().zip(FACE_COLOR_NAMES) {
+            let swatch = egui::Button::new("")
+                .fill(color32(face_color(*normal)))
+                .min_size(egui::vec2(24.0, 24.0))
+                .selected(painting.brush == *normal);
+            if ui.add(swatch).on_hover_text(name).clicked() {
+                painting.brush = *normal;
+            }
+        }
+    });
+    let mut done = false;
+    ui.horizontal(|ui| {
+        if ui.button("Clear").clicked() {
+            painting.clear();
+            repaint_cube(rubik, painting, renderer);
+            *status = None;
+        }
+        if ui.button("Check").clicked() {
+            *status = Some(match painting.state(&rubik.config) {
+                Ok(_) => String::from("The cube can be solved, ready to load"),
+                Err(e) => e,
+            });
+        }
+        if ui.button("Load").clicked() {
+            match painting.state(&rubik.config) {
+                Ok(state) => {
+                    rubik.load_state(state, renderer);
+                    *status = Some(String::from(
+                        "Loaded. The app can't solve it, the attract mode starts from a solved cube",
+                    ));
+                    done = true;
+                }
+                Err(e) => *status = Some(e),
+            }
+        }
+        if ui.button("Cancel").clicked() {
+            // the pieces get back the colours they really have
+            let state = rubik.state.clone();
+            rubik.repaint(renderer, |position, normal| {
+                state
+                    .sticker_at(position, normal)
+                    .map_or(PIECE_COLOR, |sticker| face_color(sticker.color))
+            });
+            *status = None;
+            done = true;
+        }
+    });
+    if let Some(status) = status {
+        ui.label(status.as_str());
+    }
+    if done {
+        rubik.auto_move = painting.auto_move;
+        *paint = None;
+    }
+}
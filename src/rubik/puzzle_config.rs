@@ -0,0 +1,81 @@
+use crate::rubik::rubik_state::exposed_faces;
+use glam::{IVec3, Vec3};
+
+const CUBE_SIZE: f32 = 2.0;
+const MIRROR_SILVER: u32 = 0xd4d4d8ff;
+
+/// Describes which pieces of a cube get built and how they are shaped.
+/// Pieces are addressed by their solved position, each coordinate going from
+/// `-span` to `span`.
+#[derive(Clone)]
+pub struct PuzzleConfig {
+    pub span: usize,
+    /// Leave out every piece showing a single face, this makes a void cube
+    pub remove_centers: bool,
+    /// Leave out the pieces hidden inside the cube
+    pub remove_core: bool,
+    pub removed_pieces: Vec<IVec3>,
+    /// Thickness of every layer along x, y and z, from the negative side up
+    pub layer_thickness: [Vec<f32>; 3],
+    /// Paint every sticker with this colour instead of the face colours
+    pub sticker_color: Option<u32>,
+}
+
+impl PuzzleConfig {
+    pub fn new(span: usize) -> Self {
+        let layers = vec![CUBE_SIZE; span * 2 + 1];
+        Self {
+            span,
+            remove_centers: false,
+            remove_core: true,
+            removed_pieces: Vec::new(),
+            layer_thickness: [layers.clone(), layers.clone(), layers],
+            sticker_color: None,
+        }
+    }
+    /// Mirror blocks, every axis gets thicker towards its positive side at a
+    /// different rate so no two pieces have the same shape
+    pub fn mirror(span: usize) -> Self {
+        let n = span as f32;
+        let layers = |skew: f32| {
+            (0..span * 2 + 1)
+                .map(|i| CUBE_SIZE * (1.0 + skew * (i as f32 - n) / (n + 1.0)))
+                .collect()
+        };
+        Self {
+            layer_thickness: [layers(0.3), layers(0.45), layers(0.6)],
+            sticker_color: Some(MIRROR_SILVER),
+            ..Self::new(span)
+        }
+    }
+    pub fn keeps(&self, piece: IVec3) -> bool {
+        match exposed_faces(piece, self.span) {
+            0 if self.remove_core => false,
+            1 if self.remove_centers => false,
+            _ => !self.removed_pieces.contains(&piece),
+        }
+    }
+    /// Dimensions of the piece solved at `piece`
+    pub fn piece_size(&self, piece: IVec3) -> Vec3 {
+        let n = self.span as i32;
+        Vec3::new(
+            self.layer_thickness[0][(piece.x + n) as usize],
+            self.layer_thickness[1][(piece.y + n) as usize],
+            self.layer_thickness[2][(piece.z + n) as usize],
+        )
+    }
+    /// Center of the piece solved at `piece`, with layers `margin` apart.
+    /// The middle layers sit on the origin so every turn pivots around them.
+    pub fn piece_center(&self, piece: IVec3, margin: f32) -> Vec3 {
+        let n = self.span as i32;
+        let center = |axis: usize, index: i32| {
+            let layers = &self.layer_thickness[axis];
+            let start = |i: i32| -> f32 {
+                layers[..i as usize].iter().map(|t| t + margin).sum()
+            };
+            let mid = start(n) + layers[n as usize] * 0.5;
+            start(index + n) + layers[(index + n) as usize] * 0.5 - mid
+        };
+        Vec3::new(center(0, piece.x), center(1, piece.y), center(2, piece.z))
+    }
+}
@@ -1,290 +1,681 @@
 use crate::geometry::Mesh;
+use crate::geometry::rubik_piece::PIECE_COLOR;
 use crate::material::ShaderLit;
-use crate::rubik::Move;
-use crate::world::{Node, NodeRef, Renderer, new_entity, new_group};
-use rand::Rng;
-use std::f32::consts::PI;
-use std::rc::Rc;
-use tween::{
-    BackIn, BackInOut, BackOut, BounceIn, BounceInOut, BounceOut, CircIn, CircInOut, CircOut,
-    CubicIn, CubicInOut, CubicOut, ElasticIn, ElasticInOut, ElasticOut, ExpoIn, ExpoInOut, ExpoOut,
-    Linear, QuadIn, QuadInOut, QuadOut, QuintIn, QuintInOut, QuintOut, SineIn, SineInOut, SineOut,
-    Tween, Tweener,
+use crate::rubik::rubik_state::{Cubie, FACE_NORMALS, exposed_faces};
+use crate::rubik::{AnimationSettings, Move, PuzzleConfig, RubikState, Turn};
+use crate::world::{
+    Clip, Node, NodeRef, Ray, Renderer, Track, new_entity, new_group, raycast,
 };
+use glam::{IVec3, Mat3, Quat, Vec3};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::f32::consts::FRAC_PI_2;
+use std::rc::Rc;
 
-const CUBE_SIZE: f32 = 2.0;
 const CUBE_MARGIN: f32 = 0.15;
+/// Turns kept in the history, the oldest go past that
+const HISTORY_LIMIT: usize = 200;
+/// Render layers of the pieces by kind, so a camera can look at some of them only
+pub const LAYER_CORNERS: u32 = 1 << 1;
+pub const LAYER_EDGES: u32 = 1 << 2;
+pub const LAYER_CENTERS: u32 = 1 << 3;
+pub const LAYER_CORE: u32 = 1 << 4;
 
-type GenericTween = Tweener<f32, f32, Box<dyn Tween<f32>>>;
+/// A turn in flight and the group holding the pieces it moves
+struct ActiveTurn {
+    turn: Turn,
+    /// Angle of the turn over time
+    angle: Clip<f32>,
+    /// Heading back to where it started
+    returning: bool,
+    /// Following the mouse instead of its clip, until let go
+    held: bool,
+    pieces: NodeRef,
+}
+
+impl ActiveTurn {
+    fn show(&mut self, alpha: f32) {
+        match self.turn.face {
+            Move::Top => {
+                self.pieces.rotate_z(-alpha);
+            }
+            Move::Bottom => {
+                self.pieces.rotate_z(alpha);
+            }
+            Move::Left => {
+                self.pieces.rotate_x(alpha);
+            }
+            Move::Right => {
+                self.pieces.rotate_x(-alpha);
+            }
+            Move::Front => {
+                self.pieces.rotate_y(-alpha);
+            }
+            Move::Back => {
+                self.pieces.rotate_y(alpha);
+            }
+            _ => {}
+        };
+    }
+    /// Send the turn towards `end` from wherever it is now, taking the share
+    /// of the usual duration that is left to cover
+    fn retarget(&mut self, end: f32, animation: &AnimationSettings, rng: &mut ChaCha8Rng) {
+        let alpha = self.angle.value();
+        let full = self.turn.angle();
+        let remaining = if full == 0.0 {
+            0.0
+        } else {
+            ((end - alpha) / full).abs()
+        };
+        let duration = animation.duration_for(self.turn.quarter_turns) * remaining;
+        self.angle = Clip::new(Track::tween(
+            alpha,
+            end,
+            duration.max(0.001),
+            animation.pick_easing(rng),
+        ));
+    }
+    fn reverse(&mut self, animation: &AnimationSettings, rng: &mut ChaCha8Rng) {
+        self.returning = !self.returning;
+        let end = if self.returning {
+            0.0
+        } else {
+            self.turn.angle()
+        };
+        self.retarget(end, animation, rng);
+    }
+}
+
+/// Rotation taking a piece from where it is solved to the way its stickers
+/// face now
+fn piece_rotation(cubie: &Cubie) -> Quat {
+    let mut columns = [None; 3];
+    for sticker in cubie.stickers.iter() {
+        let axis = sticker.color.abs().max_position();
+        let sign = sticker.color[axis] as f32;
+        columns[axis] = Some(sticker.normal.as_vec3() * sign);
+    }
+    // two sides known fix the third
+    for axis in 0..3 {
+        if let (None, Some(a), Some(b)) =
+            (columns[axis], columns[(axis + 1) % 3], columns[(axis + 2) % 3])
+        {
+            columns[axis] = Some(a.cross(b));
+        }
+    }
+    match (columns, cubie.stickers.as_slice()) {
+        ([Some(x), Some(y), Some(z)], _) => Quat::from_mat3(&Mat3::from_cols(x, y, z)),
+        // a center's spin doesn't show, any turn facing its sticker right will do
+        (_, [sticker]) => {
+            Quat::from_rotation_arc(sticker.color.as_vec3(), sticker.normal.as_vec3())
+        }
+        _ => Quat::IDENTITY,
+    }
+}
 
 pub struct Rubik {
-    tween: GenericTween,
-    current_move: Move,
+    /// Turns in flight, all around the same axis and on different layers
+    turns: Vec<ActiveTurn>,
     pub root: NodeRef,
-    moving_pieces: NodeRef,
     static_pieces: NodeRef,
+    /// Cubie index in `state` and the node drawing it
+    pieces: Vec<(usize, NodeRef)>,
     span: usize,
+    pub config: PuzzleConfig,
+    pub state: RubikState,
+    pub animation: AnimationSettings,
+    /// Every random choice comes from here, so a run can be replayed from its
+    /// seed. ChaCha8 is spelled out as it gives the same numbers on every
+    /// platform and rand version, which `StdRng` doesn't promise.
+    rng: ChaCha8Rng,
+    seed: u64,
+    /// Turns committed since the cube was last solved, undoing them in
+    /// reverse brings it back. Turns on the same layers in a row are kept as
+    /// one.
+    history: Vec<Turn>,
+    /// Undoing `history` solves the cube, until turns fall off its front
+    history_complete: bool,
+    /// Hide the pieces sitting in the top layer, to practise the first two
+    /// layers without seeing the rest
+    hide_last_layer: bool,
     pub paused: bool,
     pub auto_move: bool,
 }
 
 impl Rubik {
     pub fn new() -> Self {
-        let moving_cubes = new_group();
-        let static_cubes = new_group();
+        let mut static_cubes = new_group();
+        static_cubes.set_name("pieces");
         let mut root = new_group();
-        root.add_child(moving_cubes.clone());
+        root.set_name("rubik");
         root.add_child(static_cubes.clone());
+        let seed = rand::rng().random();
         Self {
-            tween: Tweener::new(0.0, PI * 2.0, 2.0, Box::new(Linear)),
-            current_move: Move::None,
+            turns: Vec::new(),
             root,
-            moving_pieces: moving_cubes,
             static_pieces: static_cubes,
+            pieces: Vec::new(),
             span: 0,
+            config: PuzzleConfig::new(0),
+            state: RubikState::new(&PuzzleConfig::new(0)),
+            animation: AnimationSettings::default(),
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            seed,
+            history: Vec::new(),
+            history_complete: true,
+            hide_last_layer: false,
             paused: false,
             auto_move: true,
         }
     }
-    pub fn generate_pieces(&mut self, span: usize, renderer: &Renderer) {
+    pub fn generate_pieces(&mut self, config: PuzzleConfig, renderer: &Renderer) {
+        self.static_pieces.extract_all_child();
+        // turns in flight belong to the old pieces, drop them without committing
+        for active in std::mem::take(&mut self.turns) {
+            self.root
+                .extract_child_if(|node| Rc::ptr_eq(node, &active.pieces));
+        }
         let shader = Rc::new(ShaderLit::new(renderer));
-        let d = CUBE_SIZE + CUBE_MARGIN;
-        self.span = span;
-        let n = span as i32;
-        for z in -n..=n {
-            for y in -n..=n {
-                for x in -n..=n {
-                    let faced_top = z == n;
-                    let faced_bottom = z == -n;
-                    let faced_left = x == -n;
-                    let faced_right = x == n;
-                    let faced_front = y == n;
-                    let faced_back = y == -n;
-                    let visible = faced_top
-                        || faced_bottom
-                        || faced_left
-                        || faced_left
-                        || faced_right
-                        || faced_front
-                        || faced_back;
-                    if !visible {
-                        continue;
-                    }
-                    let rubik_mesh = Rc::new(Mesh::new_rubik_piece(
-                        &renderer.device,
-                        faced_top,
-                        faced_bottom,
-                        faced_left,
-                        faced_right,
-                        faced_front,
-                        faced_back,
-                    ));
-                    let mut cube = new_entity(rubik_mesh, shader.clone());
-                    self.static_pieces.add_child(cube.clone());
-                    cube.translate(d * x as f32, d * y as f32, d * z as f32);
-                }
+        self.span = config.span;
+        self.state = RubikState::new(&config);
+        self.history = Vec::new();
+        self.history_complete = true;
+        self.pieces = Vec::new();
+        for (i, cubie) in self.state.cubies.iter().enumerate() {
+            if !cubie.present {
+                continue;
             }
+            let faced = [
+                IVec3::Z,
+                IVec3::NEG_Z,
+                IVec3::NEG_X,
+                IVec3::X,
+                IVec3::Y,
+                IVec3::NEG_Y,
+            ]
+            .map(|normal| cubie.stickers.iter().any(|s| s.normal == normal));
+            let rubik_mesh = Rc::new(Mesh::new_rubik_piece(
+                &renderer.device,
+                faced,
+                config.sticker_color,
+                config.piece_size(cubie.home),
+            ));
+            let mut cube = new_entity(rubik_mesh, shader.clone());
+            let home = cubie.home;
+            cube.set_name(&format!("piece {},{},{}", home.x, home.y, home.z));
+            cube.add_tag("piece");
+            let (kind, layer) = match exposed_faces(home, config.span) {
+                3 => ("corner", LAYER_CORNERS),
+                2 => ("edge", LAYER_EDGES),
+                1 => ("center", LAYER_CENTERS),
+                _ => ("core", LAYER_CORE),
+            };
+            cube.add_tag(kind);
+            cube.set_layers(layer);
+            self.static_pieces.add_child(cube.clone());
+            let p = config.piece_center(cubie.home, CUBE_MARGIN);
+            cube.translate(p.x, p.y, p.z);
+            self.pieces.push((i, cube));
         }
+        self.config = config;
+        self.update_visibility();
     }
-    pub fn start_move_random(&mut self) {
-        let mut rng = rand::rng();
-        self.current_move = Move::from(rng.random_range(0..6));
-        let size = CUBE_SIZE + CUBE_MARGIN;
-        let depth = rng.random_range(1..self.span * 2) as f32 * size * 0.5;
-        match self.current_move {
-            Move::Top => {
-                for piece in self.static_pieces.extract_child_if(|piece| {
-                    let layer = size * (self.span as f32) - piece.get_translation().z;
-                    layer < depth
-                }) {
-                    self.moving_pieces.add_child(piece.clone());
-                }
-            }
-            Move::Bottom => {
-                for piece in self.static_pieces.extract_child_if(|piece| {
-                    let layer = size * (self.span as f32) + piece.get_translation().z;
-                    layer < depth
-                }) {
-                    self.moving_pieces.add_child(piece.clone());
-                }
-            }
-            Move::Left => {
-                for piece in self.static_pieces.extract_child_if(|piece| {
-                    let layer = size * (self.span as f32) + piece.get_translation().x;
-                    layer < depth
-                }) {
-                    self.moving_pieces.add_child(piece.clone());
-                }
-            }
-            Move::Right => {
-                for piece in self.static_pieces.extract_child_if(|piece| {
-                    let layer = size * (self.span as f32) - piece.get_translation().x;
-                    layer < depth
-                }) {
-                    self.moving_pieces.add_child(piece.clone());
-                }
+    /// Show `state` instead, every piece moved and turned to where it is in
+    /// there. The history starts over from it, incomplete unless it is solved.
+    pub fn load_state(&mut self, state: RubikState, renderer: &Renderer) {
+        self.generate_pieces(self.config.clone(), renderer);
+        for (i, piece) in self.pieces.iter_mut() {
+            let cubie = &state.cubies[*i];
+            // turns pivot on the origin, so the piece ends up where turning it
+            // from home would take it, whatever shape the layers have
+            let rotation = piece_rotation(cubie);
+            let p = rotation * self.config.piece_center(cubie.home, CUBE_MARGIN);
+            piece.translate(p.x, p.y, p.z);
+            piece.rotate_quat(rotation);
+        }
+        self.state = state;
+        // no turns lead from it back to solved, the cube can't be solved
+        // by undoing them
+        self.history_complete = self.state.is_solved();
+        self.update_visibility();
+    }
+    /// Rebuild every piece with `color` for its sticker facing `normal` at
+    /// `position`, the sides without a sticker stay plain
+    pub fn repaint<F>(&mut self, renderer: &Renderer, color: F)
+    where
+        F: Fn(IVec3, IVec3) -> u32,
+    {
+        for (i, piece) in self.pieces.iter() {
+            let cubie = &self.state.cubies[*i];
+            // the mesh's sides are in the piece's own space
+            let colors = FACE_NORMALS.map(|home| {
+                cubie
+                    .stickers
+                    .iter()
+                    .find(|sticker| sticker.color == home)
+                    .map_or(PIECE_COLOR, |sticker| color(cubie.position, sticker.normal))
+            });
+            let mesh = Rc::new(Mesh::new_painted_piece(
+                &renderer.device,
+                colors,
+                self.config.piece_size(cubie.home),
+            ));
+            piece.clone().set_mesh(mesh);
+        }
+    }
+    /// The sticker `ray` hits first: the index of its piece in `state`, and
+    /// where it was hit and which way it faces in the cube's own space
+    pub fn sticker_hit(&self, ray: &Ray, layers: u32) -> Option<(usize, Vec3, IVec3)> {
+        let hit = raycast(&self.root, ray, layers)?;
+        let index = self.piece_index(&hit.node)?;
+        let local = self.root.world_transform().inverse();
+        let normal = local.transform_vector3(hit.normal);
+        let axis = normal.abs().max_position();
+        let mut face = IVec3::ZERO;
+        face[axis] = if normal[axis] > 0.0 { 1 } else { -1 };
+        // the inside of a piece isn't a sticker
+        if self.state.cubies[index].position.dot(face) != self.state.span as i32 {
+            return None;
+        }
+        Some((index, local.transform_point3(hit.position), face))
+    }
+    /// Distance from the middle of the cube to its furthest corner
+    pub fn radius(&self) -> f32 {
+        self.state
+            .cubies
+            .iter()
+            .filter(|cubie| cubie.present)
+            .map(|cubie| {
+                let center = self.config.piece_center(cubie.home, CUBE_MARGIN);
+                (center.abs() + self.config.piece_size(cubie.home) * 0.5).length()
+            })
+            .fold(0.0, f32::max)
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// Restart the random choices from `seed`. Together with a freshly
+    /// generated cube this replays the same moves every time.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+    /// `turn` can join the turns in flight: it shares their axis and none of
+    /// their layers
+    pub fn can_start(&self, turn: Turn) -> bool {
+        self.turns
+            .iter()
+            .all(|active| !active.turn.conflicts(&turn, self.span))
+    }
+    /// Move the pieces `turn` takes into a group of their own and start
+    /// animating them next to the turns already in flight. Does nothing and
+    /// returns false if the turn conflicts with one of those.
+    pub fn start_turn(&mut self, turn: Turn) -> bool {
+        if !self.can_start(turn) {
+            return false;
+        }
+        let mut group = new_group();
+        group.set_name(&format!("turn {turn}"));
+        group.add_tag("turn");
+        self.root.add_child(group.clone());
+        let layer = self.state.layer(turn);
+        let moving: Vec<&NodeRef> = self
+            .pieces
+            .iter()
+            .filter(|(i, _)| layer.contains(i))
+            .map(|(_, piece)| piece)
+            .collect();
+        for piece in self
+            .static_pieces
+            .extract_child_if(|piece| moving.iter().any(|p| Rc::ptr_eq(p, piece)))
+        {
+            group.add_child(piece);
+        }
+        self.turns.push(ActiveTurn {
+            turn,
+            angle: Clip::new(Track::tween(
+                0.0,
+                turn.angle(),
+                self.animation.duration_for(turn.quarter_turns),
+                self.animation.pick_easing(&mut self.rng),
+            )),
+            returning: false,
+            held: false,
+            pieces: group,
+        });
+        true
+    }
+    /// Start `turn` at no angle, to be moved by hand with `drag_turn` and
+    /// snapped with `release_turn`
+    pub fn grab_turn(&mut self, turn: Turn) -> bool {
+        if !self.start_turn(turn) {
+            return false;
+        }
+        let active = self.turns.last_mut().unwrap();
+        active.held = true;
+        active.angle = Clip::new(Track::new(0.0));
+        active.show(0.0);
+        true
+    }
+    /// Show the held turns at `alpha` radians clockwise
+    pub fn drag_turn(&mut self, alpha: f32) {
+        for active in self.turns.iter_mut().filter(|active| active.held) {
+            active.angle = Clip::new(Track::new(alpha));
+            active.show(alpha);
+        }
+    }
+    /// Let go of the held turns, each animating to the nearest quarter turn.
+    /// Those closer to where they started go back and don't count.
+    pub fn release_turn(&mut self) {
+        for active in self.turns.iter_mut().filter(|active| active.held) {
+            active.held = false;
+            let alpha = active.angle.value();
+            let quarter_turns = (alpha / FRAC_PI_2).round() as i32;
+            if quarter_turns == 0 {
+                active.returning = true;
+                active.retarget(0.0, &self.animation, &mut self.rng);
+            } else {
+                active.turn.quarter_turns = quarter_turns;
+                active.retarget(active.turn.angle(), &self.animation, &mut self.rng);
             }
-            Move::Front => {
-                for piece in self.static_pieces.extract_child_if(|piece| {
-                    let layer = size * (self.span as f32) - piece.get_translation().y;
-                    layer < depth
-                }) {
-                    self.moving_pieces.add_child(piece.clone());
-                }
+        }
+    }
+    /// Index in `state` of the cubie drawn by `node`
+    pub fn piece_index(&self, node: &NodeRef) -> Option<usize> {
+        self.pieces
+            .iter()
+            .find(|(_, piece)| Rc::ptr_eq(piece, node))
+            .map(|(i, _)| *i)
+    }
+    /// Turn a random set of layers around a random axis, each block of
+    /// layers by its own amount
+    pub fn start_move_random(&mut self) {
+        // sizes are drawn as u32 so 32 and 64 bit targets see the same numbers
+        let face = Move::from(self.rng.random_range(0..6));
+        let layers = self.span as u32 * 2 + 1;
+        let mut first_layer = 0;
+        while first_layer < layers {
+            let most = (self.span as u32).min(layers - first_layer).max(1);
+            let depth = self.rng.random_range(1..=most);
+            if self.rng.random_bool(0.5) {
+                let quarter_turns = self.rng.random_range(1..=3);
+                self.start_turn(Turn::slice(
+                    face,
+                    first_layer as usize,
+                    depth as usize,
+                    quarter_turns,
+                ));
             }
-            Move::Back => {
-                for piece in self.static_pieces.extract_child_if(|piece| {
-                    let layer = size * (self.span as f32) + piece.get_translation().y;
-                    layer < depth
-                }) {
-                    self.moving_pieces.add_child(piece.clone());
+            first_layer += depth;
+        }
+        if self.turns.is_empty() {
+            let turn = self.random_turn();
+            self.start_turn(turn);
+        }
+    }
+    /// Take the turn at `index` out of flight and put its pieces back in the
+    /// static group, committing it to the logical state if `commit` is set
+    /// and leaving the pieces where they started otherwise
+    fn end_turn(&mut self, index: usize, commit: bool) {
+        let mut active = self.turns.remove(index);
+        if !commit {
+            active.pieces.rotate(0.0, 0.0, 0.0);
+        }
+        let pieces = active.pieces.borrow().children.clone();
+        for piece in pieces {
+            self.static_pieces.add_child_keep_world(piece);
+        }
+        self.root
+            .extract_child_if(|node| Rc::ptr_eq(node, &active.pieces));
+        if commit {
+            self.state.apply(active.turn);
+            self.record(active.turn);
+            self.update_visibility();
+        }
+    }
+    pub fn hide_last_layer(&self) -> bool {
+        self.hide_last_layer
+    }
+    pub fn set_hide_last_layer(&mut self, hide: bool) {
+        self.hide_last_layer = hide;
+        self.update_visibility();
+    }
+    /// Show or hide each piece depending on where it sits now
+    fn update_visibility(&mut self) {
+        let top = self.span as i32;
+        for (i, piece) in self.pieces.iter_mut() {
+            let in_last_layer = self.state.cubies[*i].position.z == top;
+            piece.set_visible(!(self.hide_last_layer && in_last_layer));
+        }
+    }
+    fn record(&mut self, turn: Turn) {
+        if self.state.is_solved() {
+            self.history.clear();
+            self.history_complete = true;
+            return;
+        }
+        match self.history.last_mut() {
+            Some(last) if last.same_layers(&turn) => {
+                match (last.quarter_turns + turn.quarter_turns).rem_euclid(4) {
+                    0 => {
+                        self.history.pop();
+                    }
+                    3 => last.quarter_turns = -1,
+                    quarter_turns => last.quarter_turns = quarter_turns,
                 }
             }
-            _ => {}
-        };
-        let rotation = PI * 0.5 * rng.random_range(1..=3) as f32;
-        let t = 0.5 + 0.1 * rng.random_range(0..10) as f32;
-        match rng.random_range(0..28) {
-            0 => self.tween = Tweener::new(0.0, rotation, t, Box::new(BackIn)),
-            1 => self.tween = Tweener::new(0.0, rotation, t, Box::new(BackInOut)),
-            2 => self.tween = Tweener::new(0.0, rotation, t, Box::new(BackOut)),
-            3 => self.tween = Tweener::new(0.0, rotation, t, Box::new(BounceIn)),
-            4 => self.tween = Tweener::new(0.0, rotation, t, Box::new(BounceInOut)),
-            5 => self.tween = Tweener::new(0.0, rotation, t, Box::new(BounceOut)),
-            6 => self.tween = Tweener::new(0.0, rotation, t, Box::new(CircIn)),
-            7 => self.tween = Tweener::new(0.0, rotation, t, Box::new(CircInOut)),
-            8 => self.tween = Tweener::new(0.0, rotation, t, Box::new(CircOut)),
-            9 => self.tween = Tweener::new(0.0, rotation, t, Box::new(CubicIn)),
-            10 => self.tween = Tweener::new(0.0, rotation, t, Box::new(CubicInOut)),
-            11 => self.tween = Tweener::new(0.0, rotation, t, Box::new(CubicOut)),
-            12 => self.tween = Tweener::new(0.0, rotation, t, Box::new(ElasticIn)),
-            13 => self.tween = Tweener::new(0.0, rotation, t, Box::new(ElasticInOut)),
-            14 => self.tween = Tweener::new(0.0, rotation, t, Box::new(ElasticOut)),
-            15 => self.tween = Tweener::new(0.0, rotation, t, Box::new(ExpoIn)),
-            16 => self.tween = Tweener::new(0.0, rotation, t, Box::new(ExpoInOut)),
-            17 => self.tween = Tweener::new(0.0, rotation, t, Box::new(ExpoOut)),
-            18 => self.tween = Tweener::new(0.0, rotation, t, Box::new(QuadIn)),
-            19 => self.tween = Tweener::new(0.0, rotation, t, Box::new(QuadInOut)),
-            20 => self.tween = Tweener::new(0.0, rotation, t, Box::new(QuadOut)),
-            21 => self.tween = Tweener::new(0.0, rotation, t, Box::new(QuintIn)),
-            22 => self.tween = Tweener::new(0.0, rotation, t, Box::new(QuintInOut)),
-            23 => self.tween = Tweener::new(0.0, rotation, t, Box::new(QuintOut)),
-            24 => self.tween = Tweener::new(0.0, rotation, t, Box::new(SineIn)),
-            25 => self.tween = Tweener::new(0.0, rotation, t, Box::new(SineInOut)),
-            26 => self.tween = Tweener::new(0.0, rotation, t, Box::new(SineOut)),
-            _ => self.tween = Tweener::new(0.0, rotation, t, Box::new(Linear)),
-        };
+            _ => self.history.push(turn),
+        }
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.remove(0);
+            self.history_complete = false;
+        }
     }
-    pub fn finish_move(&mut self) {
-        let mat = self.moving_pieces.calculate_transform();
-        for mut piece in self.moving_pieces.extract_all_child() {
-            let mat = mat * piece.calculate_transform();
-            let (_scale, rotation, translation) = mat.to_scale_rotation_translation();
-            piece.translate(translation.x, translation.y, translation.z);
-            piece.rotate_quat(rotation);
-            self.static_pieces.add_child(piece);
+    pub fn history(&self) -> &[Turn] {
+        &self.history
+    }
+    /// Undoing `history` in reverse brings the cube back to solved
+    pub fn history_complete(&self) -> bool {
+        self.history_complete
+    }
+    /// Put every piece back home at once, the cube solved and its history
+    /// empty
+    pub fn reset(&mut self) {
+        self.cancel_turns();
+        self.state = RubikState::new(&self.config);
+        for (i, piece) in self.pieces.iter_mut() {
+            let p = self.config.piece_center(self.state.cubies[*i].home, CUBE_MARGIN);
+            piece.translate(p.x, p.y, p.z);
+            piece.rotate_quat(Quat::IDENTITY);
+        }
+        self.history.clear();
+        self.history_complete = true;
+        self.update_visibility();
+    }
+    /// The cube's own random numbers, for choices that should replay with
+    /// its seed
+    pub fn rng(&mut self) -> &mut ChaCha8Rng {
+        &mut self.rng
+    }
+    /// A single random turn of the outer layers of a face
+    pub fn random_turn(&mut self) -> Turn {
+        let face = Move::from(self.rng.random_range(0..6));
+        let depth = self.rng.random_range(1..=self.span as u32);
+        let quarter_turns = self.rng.random_range(1..=3);
+        Turn::new(face, depth as usize, quarter_turns)
+    }
+    pub fn is_turning(&self) -> bool {
+        !self.turns.is_empty()
+    }
+    /// Seconds the longest turn in flight takes
+    pub fn turn_duration(&self) -> f32 {
+        self.turns
+            .iter()
+            .map(|active| active.angle.duration())
+            .fold(0.0, f32::max)
+    }
+    /// Jump the turns in flight to `time` seconds from their start
+    pub fn seek(&mut self, time: f32) {
+        for active in self.turns.iter_mut().filter(|active| !active.held) {
+            let alpha = active.angle.move_to(time);
+            active.show(alpha);
         }
-        self.moving_pieces.rotate(0.0, 0.0, 0.0);
+    }
+    /// Jump to the end of the turns in flight, committing those that weren't
+    /// on their way back
+    pub fn settle(&mut self) {
+        self.release_turn();
+        while let Some(active) = self.turns.last_mut() {
+            let alpha = active.angle.move_to(active.angle.duration());
+            active.show(alpha);
+            let commit = !active.returning;
+            self.end_turn(self.turns.len() - 1, commit);
+        }
+    }
+    /// Drop the turns in flight, leaving the pieces where they started
+    pub fn cancel_turns(&mut self) {
+        while !self.turns.is_empty() {
+            self.end_turn(self.turns.len() - 1, false);
+        }
+    }
+    /// Turn the turns in flight around, towards their start or their end
+    pub fn reverse_move(&mut self) {
+        for active in self.turns.iter_mut().filter(|active| !active.held) {
+            active.reverse(&self.animation, &mut self.rng);
+        }
+    }
+    /// Animate the turns in flight back to where they started, they won't count
+    pub fn cancel_move(&mut self) {
+        for active in self
+            .turns
+            .iter_mut()
+            .filter(|active| !active.returning && !active.held)
+        {
+            active.reverse(&self.animation, &mut self.rng);
+        }
+    }
+    /// Cut the turns in flight short and carry on
+    pub fn finish_now(&mut self) {
+        self.settle();
         if self.auto_move {
             self.start_move_random();
         }
     }
-    pub fn perform_move(&mut self, move_type: Move) {
-        if self.tween.is_finished() {
-            self.current_move = move_type;
-            let size = CUBE_SIZE + CUBE_MARGIN;
-            let threshold = size * (self.span as f32 - 0.5);
-
-            match self.current_move {
-                Move::Top => {
-                    // Top face - highest Z layer
-                    for piece in self
-                        .static_pieces
-                        .extract_child_if(|piece| piece.get_translation().z > threshold)
-                    {
-                        self.moving_pieces.add_child(piece.clone());
-                    }
-                }
-                Move::Bottom => {
-                    // Bottom face - lowest Z layer
-                    for piece in self
-                        .static_pieces
-                        .extract_child_if(|piece| piece.get_translation().z < -threshold)
-                    {
-                        self.moving_pieces.add_child(piece.clone());
-                    }
-                }
-                Move::Left => {
-                    // Left face - lowest X layer
-                    for piece in self
-                        .static_pieces
-                        .extract_child_if(|piece| piece.get_translation().x < -threshold)
-                    {
-                        self.moving_pieces.add_child(piece.clone());
-                    }
-                }
-                Move::Right => {
-                    // Right face - highest X layer
-                    for piece in self
-                        .static_pieces
-                        .extract_child_if(|piece| piece.get_translation().x > threshold)
-                    {
-                        self.moving_pieces.add_child(piece.clone());
-                    }
-                }
-                Move::Front => {
-                    // Front face - highest Y layer
-                    for piece in self
-                        .static_pieces
-                        .extract_child_if(|piece| piece.get_translation().y > threshold)
-                    {
-                        self.moving_pieces.add_child(piece.clone());
-                    }
-                }
-                Move::Back => {
-                    // Back face - lowest Y layer
-                    for piece in self
-                        .static_pieces
-                        .extract_child_if(|piece| piece.get_translation().y < -threshold)
-                    {
-                        self.moving_pieces.add_child(piece.clone());
-                    }
-                }
-                _ => {}
-            };
-
-            self.tween = Tweener::new(0.0, PI * 0.5, 0.5, Box::new(CubicInOut));
+    /// Commit `turn` without animating it
+    pub fn apply_instant(&mut self, turn: Turn) {
+        self.settle();
+        self.start_turn(turn);
+        self.settle();
+    }
+    /// Start `turn` right away, cutting the turns in flight short if it
+    /// can't run alongside them
+    pub fn turn_now(&mut self, turn: Turn) {
+        if !self.start_turn(turn) {
+            self.settle();
+            self.start_turn(turn);
         }
     }
+    /// Turn the outer layer of `move_type` a quarter, alongside the turns in
+    /// flight if it doesn't get in their way
+    pub fn perform_move(&mut self, move_type: Move) {
+        self.start_turn(Turn::new(move_type, 1, 1));
+    }
 
     pub fn update(&mut self, delta_time: f32) {
         if self.paused {
             return;
         }
-        let alpha = self.tween.move_by(delta_time);
-        match self.current_move {
-            Move::Top => {
-                self.moving_pieces.rotate_z(-alpha);
-            }
-            Move::Bottom => {
-                self.moving_pieces.rotate_z(alpha);
-            }
-            Move::Left => {
-                self.moving_pieces.rotate_x(alpha);
-            }
-            Move::Right => {
-                self.moving_pieces.rotate_x(-alpha);
-            }
-            Move::Front => {
-                self.moving_pieces.rotate_y(-alpha);
-            }
-            Move::Back => {
-                self.moving_pieces.rotate_y(alpha);
+        for active in self.turns.iter_mut().filter(|active| !active.held) {
+            let alpha = active.angle.move_by(delta_time);
+            active.show(alpha);
+        }
+        for i in (0..self.turns.len()).rev() {
+            if !self.turns[i].held && self.turns[i].angle.is_finished() {
+                let commit = !self.turns[i].returning;
+                self.end_turn(i, commit);
             }
-            _ => {}
+        }
+        if self.turns.is_empty() && self.auto_move {
+            self.start_move_random();
+        }
+    }
+}
+
+#[cfg(test)]
+impl Rubik {
+    /// A cube with the logical state of `config` and no pieces to draw, so
+    /// tests don't need a GPU
+    pub(crate) fn headless(config: PuzzleConfig) -> Self {
+        let mut rubik = Self::new();
+        rubik.span = config.span;
+        rubik.state = RubikState::new(&config);
+        rubik.config = config;
+        rubik
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_turns(seed: u64, count: usize) -> Vec<String> {
+        let mut rubik = Rubik::headless(PuzzleConfig::new(2));
+        rubik.reseed(seed);
+        (0..count).map(|_| rubik.random_turn().to_string()).collect()
+    }
+
+    #[test]
+    fn a_seed_replays_its_turns() {
+        assert_eq!(random_turns(42, 50), random_turns(42, 50));
+        assert_ne!(random_turns(42, 50), random_turns(43, 50));
+        // ChaCha8 is the same everywhere, so these are too
+        let expected = ["Dw", "Bw2", "Lw'", "Dw", "D", "Fw'", "F2", "R'"];
+        assert_eq!(random_turns(42, 8), expected);
+    }
+
+    #[test]
+    fn history_folds_turns_on_the_same_layers() {
+        let mut rubik = Rubik::headless(PuzzleConfig::new(1));
+        let history = |rubik: &Rubik| -> Vec<String> {
+            rubik.history().iter().map(Turn::to_string).collect()
         };
-        if self.tween.is_finished() {
-            self.finish_move();
+        for text in ["R", "U", "U", "U"] {
+            rubik.apply_instant(Turn::parse(text).unwrap());
+        }
+        assert_eq!(history(&rubik), ["R", "U'"]);
+        rubik.apply_instant(Turn::parse("U").unwrap());
+        assert_eq!(history(&rubik), ["R"]);
+        rubik.apply_instant(Turn::parse("R'").unwrap());
+        assert!(history(&rubik).is_empty());
+
+        rubik.reseed(1);
+        for _ in 0..HISTORY_LIMIT * 2 {
+            let turn = rubik.random_turn();
+            rubik.apply_instant(turn);
+        }
+        assert!(rubik.history().len() <= HISTORY_LIMIT);
+        assert!(!rubik.history_complete());
+        rubik.reset();
+        assert!(rubik.state.is_solved());
+        assert!(rubik.history_complete());
+    }
+
+    #[test]
+    fn piece_rotation_follows_the_turns() {
+        let config = PuzzleConfig::new(1);
+        let mut state = RubikState::new(&config);
+        for text in "R U' M F2 E' L S B2 x D'".split_whitespace() {
+            state.apply(Turn::parse_on(text, 1).unwrap());
+        }
+        for cubie in state.cubies.iter().filter(|cubie| !cubie.stickers.is_empty()) {
+            let rotation = piece_rotation(cubie);
+            let placed = rotation * config.piece_center(cubie.home, CUBE_MARGIN);
+            let expected = config.piece_center(cubie.position, CUBE_MARGIN);
+            assert!(placed.abs_diff_eq(expected, 1e-4));
+            for sticker in cubie.stickers.iter() {
+                let normal = rotation * sticker.color.as_vec3();
+                assert!(normal.abs_diff_eq(sticker.normal.as_vec3(), 1e-4));
+            }
         }
     }
 }
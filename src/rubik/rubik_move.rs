@@ -1,6 +1,9 @@
+use glam::IVec3;
 use std::convert::From;
+use std::f32::consts::PI;
+use std::fmt;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum Move {
     Top,
     Bottom,
@@ -24,3 +27,278 @@ impl From<i32> for Move {
         }
     }
 }
+
+impl Move {
+    /// Outward normal of the face this move turns
+    pub fn axis(&self) -> IVec3 {
+        match self {
+            Move::Top => IVec3::Z,
+            Move::Bottom => IVec3::NEG_Z,
+            Move::Left => IVec3::NEG_X,
+            Move::Right => IVec3::X,
+            Move::Front => IVec3::Y,
+            Move::Back => IVec3::NEG_Y,
+            Move::None => IVec3::ZERO,
+        }
+    }
+    pub fn letter(&self) -> &'static str {
+        match self {
+            Move::Top => "U",
+            Move::Bottom => "D",
+            Move::Left => "L",
+            Move::Right => "R",
+            Move::Front => "F",
+            Move::Back => "B",
+            Move::None => "",
+        }
+    }
+}
+
+/// A face move together with which layers it takes and how far it goes.
+/// Layers are counted from the face, the outer layer being 0.
+#[derive(Clone, Copy)]
+pub struct Turn {
+    pub face: Move,
+    pub first_layer: usize,
+    pub depth: usize,
+    pub quarter_turns: i32,
+}
+
+impl Turn {
+    pub fn new(face: Move, depth: usize, quarter_turns: i32) -> Self {
+        Self {
+            face,
+            first_layer: 0,
+            depth,
+            quarter_turns,
+        }
+    }
+    /// Turn `depth` layers starting `first_layer` layers in from the face
+    pub fn slice(face: Move, first_layer: usize, depth: usize, quarter_turns: i32) -> Self {
+        Self {
+            face,
+            first_layer,
+            depth,
+            quarter_turns,
+        }
+    }
+    /// Axis the turn goes around, and the range of piece coordinates along
+    /// that axis it takes, on a cube of the given span
+    pub fn layers(&self, span: usize) -> Option<(usize, i32, i32)> {
+        let normal = self.face.axis();
+        let axis = normal.abs().to_array().iter().position(|v| *v != 0)?;
+        let outer = span as i32 - self.first_layer as i32;
+        let inner = outer - self.depth as i32 + 1;
+        if normal[axis] > 0 {
+            Some((axis, inner, outer))
+        } else {
+            Some((axis, -outer, -inner))
+        }
+    }
+    /// Two turns can only run together on the same axis with no shared layer
+    pub fn conflicts(&self, other: &Turn, span: usize) -> bool {
+        match (self.layers(span), other.layers(span)) {
+            (Some((a, lo_a, hi_a)), Some((b, lo_b, hi_b))) => {
+                a != b || (lo_a <= hi_b && lo_b <= hi_a)
+            }
+            _ => false,
+        }
+    }
+    /// Clockwise angle of the turn when looking at the face
+    pub fn angle(&self) -> f32 {
+        PI * 0.5 * self.quarter_turns as f32
+    }
+    pub fn inverse(&self) -> Turn {
+        Turn {
+            quarter_turns: -self.quarter_turns,
+            ..*self
+        }
+    }
+    /// `other` turns the very same layers the same way round, the two add
+    /// up to a single turn
+    pub fn same_layers(&self, other: &Turn) -> bool {
+        self.face == other.face
+            && self.first_layer == other.first_layer
+            && self.depth == other.depth
+    }
+    /// The turn only takes layers a cube of `span` has
+    pub fn fits(&self, span: usize) -> bool {
+        self.depth > 0 && self.first_layer + self.depth <= span * 2 + 1
+    }
+    /// Parse a single move in standard notation, like `R`, `U'`, `F2`, `Rw`,
+    /// `r`, `3Rw'`, `2R` or `2-3Rw`. Wide moves take 2 layers unless a count
+    /// is given, a count on a plain move picks that single inner layer and a
+    /// range picks the layers from its first to its last.
+    pub fn parse(text: &str) -> Option<Turn> {
+        let split = text
+            .find(|c: char| !c.is_ascii_digit() && c != '-')
+            .unwrap_or(text.len());
+        let (digits, rest) = text.split_at(split);
+        let mut chars = rest.chars();
+        let letter = chars.next()?;
+        let face = match letter.to_ascii_uppercase() {
+            'U' => Move::Top,
+            'D' => Move::Bottom,
+            'L' => Move::Left,
+            'R' => Move::Right,
+            'F' => Move::Front,
+            'B' => Move::Back,
+            _ => return None,
+        };
+        let mut rest = chars.as_str();
+        let mut wide = letter.is_ascii_lowercase();
+        if let Some(stripped) = rest.strip_prefix('w') {
+            wide = true;
+            rest = stripped;
+        }
+        let (first_layer, depth) = match digits.split_once('-') {
+            Some((first, last)) => {
+                let first = first.parse::<usize>().ok()?.checked_sub(1)?;
+                (first, last.parse::<usize>().ok()?.checked_sub(first)?)
+            }
+            None => match (wide, digits.is_empty()) {
+                (false, true) => (0, 1),
+                (true, true) => (0, 2),
+                (true, false) => (0, digits.parse().ok()?),
+                (false, false) => (digits.parse::<usize>().ok()?.checked_sub(1)?, 1),
+            },
+        };
+        if depth == 0 {
+            return None;
+        }
+        let quarter_turns = match rest {
+            "" => 1,
+            "'" => -1,
+            "2" | "2'" => 2,
+            _ => return None,
+        };
+        Some(Turn::slice(face, first_layer, depth, quarter_turns))
+    }
+    /// Like `parse`, also taking the moves that depend on the cube size:
+    /// whole cube rotations `x`, `y`, `z` and middle slices `M`, `E`, `S`
+    pub fn parse_on(text: &str, span: usize) -> Option<Turn> {
+        let mut chars = text.chars();
+        let (face, first_layer, depth) = match chars.next()? {
+            'x' => (Move::Right, 0, span * 2 + 1),
+            'y' => (Move::Top, 0, span * 2 + 1),
+            'z' => (Move::Front, 0, span * 2 + 1),
+            'M' if span > 0 => (Move::Left, 1, span * 2 - 1),
+            'E' if span > 0 => (Move::Bottom, 1, span * 2 - 1),
+            'S' if span > 0 => (Move::Front, 1, span * 2 - 1),
+            _ => return Turn::parse(text),
+        };
+        let quarter_turns = match chars.as_str() {
+            "" => 1,
+            "'" => -1,
+            "2" | "2'" => 2,
+            _ => return None,
+        };
+        Some(Turn::slice(face, first_layer, depth, quarter_turns))
+    }
+    /// Parse moves separated by spaces with `parse_on`, every one of them
+    /// has to fit a cube of `span`
+    pub fn parse_sequence(text: &str, span: usize) -> Result<Vec<Turn>, String> {
+        text.split_whitespace()
+            .map(|word| {
+                let turn = Turn::parse_on(word, span).ok_or(format!("unknown move {word}"))?;
+                if !turn.fits(span) {
+                    return Err(format!("{word} takes layers the cube doesn't have"));
+                }
+                Ok(turn)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Turn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.first_layer, self.depth) {
+            (0, 1) => write!(f, "{}", self.face.letter())?,
+            (0, 2) => write!(f, "{}w", self.face.letter())?,
+            (0, depth) => write!(f, "{}{}w", depth, self.face.letter())?,
+            (first, 1) => write!(f, "{}{}", first + 1, self.face.letter())?,
+            (first, depth) => write!(
+                f,
+                "{}-{}{}w",
+                first + 1,
+                first + depth,
+                self.face.letter()
+            )?,
+        }
+        match self.quarter_turns.rem_euclid(4) {
+            2 => write!(f, "2"),
+            3 => write!(f, "'"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn same(a: &Turn, b: &Turn) -> bool {
+        a.face == b.face
+            && a.first_layer == b.first_layer
+            && a.depth == b.depth
+            && (a.quarter_turns - b.quarter_turns).rem_euclid(4) == 0
+    }
+
+    #[test]
+    fn parse_reads_every_form() {
+        let cases = [
+            ("R", Move::Right, 0, 1, 1),
+            ("U'", Move::Top, 0, 1, -1),
+            ("F2", Move::Front, 0, 1, 2),
+            ("Rw", Move::Right, 0, 2, 1),
+            ("r'", Move::Right, 0, 2, -1),
+            ("3Lw", Move::Left, 0, 3, 1),
+            ("2D", Move::Bottom, 1, 1, 1),
+            ("2-3Bw2", Move::Back, 1, 2, 2),
+        ];
+        for (text, face, first_layer, depth, quarter_turns) in cases {
+            let turn = Turn::parse(text).unwrap();
+            assert!(same(&turn, &Turn::slice(face, first_layer, depth, quarter_turns)), "{text}");
+        }
+        for text in ["", "Q", "R3", "0R", "0Rw", "3-2Rw", "-Rw", "R''"] {
+            assert!(Turn::parse(text).is_none(), "{text}");
+        }
+    }
+
+    #[test]
+    fn display_and_parse_round_trip() {
+        for face in [Move::Top, Move::Bottom, Move::Left, Move::Right, Move::Front, Move::Back] {
+            for first_layer in 0..4 {
+                for depth in 1..4 {
+                    for quarter_turns in [1, 2, 3, -1] {
+                        let turn = Turn::slice(face, first_layer, depth, quarter_turns);
+                        let text = turn.to_string();
+                        let parsed = Turn::parse(&text).unwrap();
+                        assert!(same(&turn, &parsed), "{text}");
+                        assert_eq!(parsed.to_string(), text);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parse_on_reads_slices_and_rotations() {
+        let m = Turn::parse_on("M", 1).unwrap();
+        assert!(same(&m, &Turn::slice(Move::Left, 1, 1, 1)));
+        let x = Turn::parse_on("x'", 2).unwrap();
+        assert!(same(&x, &Turn::slice(Move::Right, 0, 5, -1)));
+        assert!(same(&Turn::parse_on("R2", 1).unwrap(), &Turn::parse("R2").unwrap()));
+    }
+
+    #[test]
+    fn sequence_rejects_layers_outside_the_cube() {
+        let moves = Turn::parse_sequence("R U' M x2 3Rw 2-3Lw", 1).unwrap();
+        assert_eq!(moves.len(), 6);
+        for text in ["4R", "4Rw", "3-4Rw", "R 2-5Uw"] {
+            assert!(Turn::parse_sequence(text, 1).is_err(), "{text}");
+        }
+        assert!(Turn::parse_sequence("4R", 2).is_ok());
+        assert!(Turn::parse_sequence("R Q", 1).is_err());
+    }
+}
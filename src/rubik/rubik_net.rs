@@ -0,0 +1,320 @@
+use crate::geometry::Mesh;
+use crate::geometry::rubik_piece::FACE_COLORS;
+use crate::material::{Shader, ShaderUnlit};
+use crate::rubik::rubik_state::FACE_NORMALS;
+use crate::rubik::{Move, Rubik};
+use crate::world::{Camera, Node, NodeRef, Projection, Renderer, new_entity, new_group};
+use glam::{IVec3, Vec2};
+use std::rc::Rc;
+
+/// Space between two faces of the net, in stickers
+const FACE_GAP: f32 = 0.25;
+/// Colour of a facelet with no piece behind it
+pub const EMPTY_COLOR: u32 = 0x313244ff;
+const HIGHLIGHT_COLOR: u32 = 0xffffffff;
+
+/// Faces of the net with the column and row they take, a cross around the
+/// front face with the top above it. Going right from the front comes the
+/// left face, that is where this cube's turns put it.
+const NET_FACES: [(Move, usize, usize); 6] = [
+    (Move::Top, 1, 0),
+    (Move::Right, 0, 1),
+    (Move::Front, 1, 1),
+    (Move::Left, 2, 1),
+    (Move::Back, 3, 1),
+    (Move::Bottom, 1, 2),
+];
+
+/// Which ways the net's right and up point on `face`, seen from outside
+fn face_frame(face: Move) -> (IVec3, IVec3) {
+    let up = match face {
+        Move::Top => IVec3::NEG_Y,
+        Move::Bottom => IVec3::Y,
+        _ => IVec3::Z,
+    };
+    (up.cross(face.axis()), up)
+}
+
+/// Colour of the stickers solved on the face with this outward `normal`
+pub fn face_color(normal: IVec3) -> u32 {
+    FACE_NORMALS
+        .iter()
+        .position(|n| *n == normal)
+        .map_or(EMPTY_COLOR, |i| FACE_COLORS[i])
+}
+
+/// Width and height of the whole net of a cube of `span`, in stickers
+pub fn net_size(span: usize) -> Vec2 {
+    let stickers = (span * 2 + 1) as f32;
+    Vec2::new(stickers * 4.0 + FACE_GAP * 3.0, stickers * 3.0 + FACE_GAP * 2.0)
+}
+
+/// A sticker's place on the net: a face, and a row and column counted from
+/// the top left of that face as the net shows it
+#[derive(Clone, Copy, PartialEq)]
+pub struct Facelet {
+    pub face: Move,
+    pub row: usize,
+    pub column: usize,
+}
+
+impl Facelet {
+    /// Every facelet of a cube of `span`, face by face
+    pub fn all(span: usize) -> Vec<Facelet> {
+        let stickers = span * 2 + 1;
+        NET_FACES
+            .iter()
+            .flat_map(|(face, _, _)| {
+                (0..stickers * stickers).map(move |i| Facelet {
+                    face: *face,
+                    row: i / stickers,
+                    column: i % stickers,
+                })
+            })
+            .collect()
+    }
+    /// The facelet under `point` of the net, measured in stickers from its
+    /// top left corner
+    pub fn at(point: Vec2, span: usize) -> Option<Facelet> {
+        let stickers = (span * 2 + 1) as f32;
+        NET_FACES.iter().find_map(|(face, column, row)| {
+            let origin = Vec2::new(*column as f32, *row as f32) * (stickers + FACE_GAP);
+            let local = point - origin;
+            if local.min_element() < 0.0 || local.max_element() >= stickers {
+                return None;
+            }
+            Some(Facelet {
+                face: *face,
+                row: local.y as usize,
+                column: local.x as usize,
+            })
+        })
+    }
+    /// The facelet showing the side facing `normal` of the piece at `position`
+    pub fn from_sticker(position: IVec3, normal: IVec3, span: usize) -> Option<Facelet> {
+        let n = span as i32;
+        let face = NET_FACES
+            .iter()
+            .map(|(face, _, _)| *face)
+            .find(|face| face.axis() == normal)?;
+        if position.dot(normal) != n {
+            return None;
+        }
+        let (right, up) = face_frame(face);
+        Some(Facelet {
+            face,
+            row: (n - position.dot(up)) as usize,
+            column: (position.dot(right) + n) as usize,
+        })
+    }
+    /// Where the facelet comes in `Facelet::all`
+    pub fn index(&self, span: usize) -> usize {
+        let stickers = span * 2 + 1;
+        let face = NET_FACES
+            .iter()
+            .position(|(face, _, _)| *face == self.face)
+            .unwrap_or(0);
+        (face * stickers + self.row) * stickers + self.column
+    }
+    /// Outward normal of the facelet
+    pub fn normal(&self) -> IVec3 {
+        self.face.axis()
+    }
+    /// Position of the piece the facelet sits on
+    pub fn position(&self, span: usize) -> IVec3 {
+        let n = span as i32;
+        let (right, up) = face_frame(self.face);
+        self.normal() * n + right * (self.column as i32 - n) - up * (self.row as i32 - n)
+    }
+    /// Top left corner of the facelet in the net, in stickers
+    pub fn corner(&self, span: usize) -> Vec2 {
+        let stickers = (span * 2 + 1) as f32;
+        let (_, column, row) = NET_FACES
+            .iter()
+            .find(|(face, _, _)| *face == self.face)
+            .copied()
+            .unwrap_or((self.face, 0, 0));
+        Vec2::new(column as f32, row as f32) * (stickers + FACE_GAP)
+            + Vec2::new(self.column as f32, self.row as f32)
+    }
+    /// Colour `rubik` shows at the facelet, None with no piece there
+    pub fn color(&self, rubik: &Rubik) -> Option<u32> {
+        let sticker = rubik.state.sticker_at(self.position(rubik.state.span), self.normal())?;
+        Some(rubik.config.sticker_color.unwrap_or(face_color(sticker.color)))
+    }
+}
+
+/// The net as flat squares in a scene of its own, for a camera looking
+/// straight at it
+pub struct RubikNet {
+    pub root: NodeRef,
+    pub selected: Option<Facelet>,
+    span: usize,
+    sticker_color: Option<u32>,
+    stickers: Vec<(Facelet, NodeRef)>,
+    /// A square of every colour, swapped onto the stickers as they change
+    squares: Vec<(u32, Rc<Mesh>)>,
+    shader: Option<Rc<dyn Shader>>,
+    highlight: NodeRef,
+}
+
+impl RubikNet {
+    pub fn new() -> Self {
+        let mut root = new_group();
+        root.set_name("net");
+        Self {
+            root,
+            selected: None,
+            span: 0,
+            sticker_color: None,
+            stickers: Vec::new(),
+            squares: Vec::new(),
+            shader: None,
+            highlight: new_group(),
+        }
+    }
+    /// Build the squares for the cube `rubik` has now
+    fn generate(&mut self, rubik: &Rubik, renderer: &Renderer) {
+        self.root.extract_all_child();
+        let shader: Rc<dyn Shader> = self
+            .shader
+            .get_or_insert_with(|| Rc::new(ShaderUnlit::new(renderer)))
+            .clone();
+        self.span = rubik.state.span;
+        self.sticker_color = rubik.config.sticker_color;
+        self.squares = FACE_COLORS
+            .iter()
+            .chain(rubik.config.sticker_color.iter())
+            .chain([EMPTY_COLOR, HIGHLIGHT_COLOR].iter())
+            .map(|color| (*color, Rc::new(Mesh::new_cube(*color, &renderer.device))))
+            .collect();
+        // the net lies on the XY plane, centered, with its rows going down
+        let center = net_size(self.span) * 0.5;
+        self.stickers = Vec::new();
+        for facelet in Facelet::all(self.span) {
+            let mut node = new_entity(self.square(EMPTY_COLOR), shader.clone());
+            node.set_name(&format!(
+                "{} {},{}",
+                facelet.face.letter(),
+                facelet.row,
+                facelet.column
+            ));
+            let p = facelet.corner(self.span) + Vec2::splat(0.5) - center;
+            node.translate(p.x, -p.y, 0.0);
+            node.scale(0.45, 0.45, 0.05);
+            self.root.add_child(node.clone());
+            self.stickers.push((facelet, node));
+        }
+        self.highlight = new_entity(self.square(HIGHLIGHT_COLOR), shader);
+        self.highlight.set_name("selected");
+        self.highlight.scale(0.55, 0.55, 0.05);
+        self.highlight.set_visible(false);
+        self.root.add_child(self.highlight.clone());
+    }
+    fn square(&self, color: u32) -> Rc<Mesh> {
+        self.squares
+            .iter()
+            .find(|(c, _)| *c == color)
+            .or_else(|| self.squares.iter().find(|(c, _)| *c == EMPTY_COLOR))
+            .map(|(_, mesh)| mesh.clone())
+            .expect("the empty square is always built")
+    }
+    /// Colour the squares with `color` of every facelet, rebuilding them if
+    /// `rubik` changed size. None leaves a facelet empty.
+    pub fn sync<F>(&mut self, rubik: &Rubik, renderer: &Renderer, color: F)
+    where
+        F: Fn(&Facelet) -> Option<u32>,
+    {
+        if self.stickers.is_empty()
+            || self.span != rubik.state.span
+            || self.sticker_color != rubik.config.sticker_color
+        {
+            self.generate(rubik, renderer);
+        }
+        for (facelet, node) in self.stickers.iter() {
+            let square = self.square(color(facelet).unwrap_or(EMPTY_COLOR));
+            node.clone().set_mesh(square);
+        }
+        let selected = self
+            .selected
+            .and_then(|facelet| self.stickers.iter().find(|(f, _)| *f == facelet));
+        match selected {
+            Some((_, node)) => {
+                let p = node.borrow().translation;
+                let mut highlight = self.highlight.clone();
+                highlight.set_visible(true);
+                highlight.translate(p.x, p.y, -0.2);
+            }
+            None => self.highlight.clone().set_visible(false),
+        }
+    }
+    /// The facelet a square of the net belongs to
+    pub fn facelet(&self, node: &NodeRef) -> Option<Facelet> {
+        self.stickers
+            .iter()
+            .find(|(_, n)| Rc::ptr_eq(n, node))
+            .map(|(facelet, _)| *facelet)
+    }
+    /// Point an orthographic `camera` at the net, filling a view of `aspect_ratio`
+    pub fn fit_camera(&self, camera: &mut Camera, aspect_ratio: f32) {
+        let size = net_size(self.span);
+        let half_height = (size.y * 0.5).max(size.x * 0.5 / aspect_ratio) * 1.05;
+        camera.projection = Projection::Orthographic;
+        camera.target = glam::Vec3::ZERO;
+        camera.set_orbit(half_height / (camera.fov * 0.5).tan(), 0.0, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_and_index_agree() {
+        for span in [1, 2] {
+            let stickers = span * 2 + 1;
+            let all = Facelet::all(span);
+            assert_eq!(all.len(), 6 * stickers * stickers);
+            for (i, facelet) in all.iter().enumerate() {
+                assert_eq!(facelet.index(span), i);
+                assert!(all[facelet.index(span)] == *facelet);
+            }
+        }
+    }
+
+    #[test]
+    fn from_sticker_undoes_position() {
+        for span in [1, 2] {
+            let n = span as i32;
+            for facelet in Facelet::all(span) {
+                let position = facelet.position(span);
+                assert_eq!(position.dot(facelet.normal()), n);
+                assert!(position.abs().max_element() == n);
+                let back = Facelet::from_sticker(position, facelet.normal(), span);
+                assert!(back == Some(facelet));
+            }
+            // inside the cube, and looking along the face instead of out of it
+            assert!(Facelet::from_sticker(IVec3::new(n - 1, 0, 0), IVec3::X, span).is_none());
+            assert!(Facelet::from_sticker(IVec3::new(n, n, 0), IVec3::Z, span).is_none());
+        }
+    }
+
+    #[test]
+    fn at_finds_the_facelet_at_its_corner() {
+        for span in [1, 2] {
+            for facelet in Facelet::all(span) {
+                let corner = facelet.corner(span);
+                assert!(Facelet::at(corner + Vec2::splat(0.5), span) == Some(facelet));
+                let size = net_size(span);
+                assert!(corner.x >= 0.0 && corner.y >= 0.0);
+                assert!(corner.x + 1.0 <= size.x && corner.y + 1.0 <= size.y);
+            }
+            let stickers = (span * 2 + 1) as f32;
+            // between two faces, left of the top face, and past the net
+            assert!(Facelet::at(Vec2::new(stickers + FACE_GAP * 0.5, stickers + 0.5), span).is_none());
+            assert!(Facelet::at(Vec2::new(0.5, 0.5), span).is_none());
+            assert!(Facelet::at(net_size(span) + Vec2::ONE, span).is_none());
+            assert!(Facelet::at(Vec2::splat(-0.5), span).is_none());
+        }
+    }
+}
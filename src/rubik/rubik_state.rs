@@ -0,0 +1,251 @@
+use crate::rubik::{PuzzleConfig, Turn};
+use glam::IVec3;
+
+/// Face normals in top, bottom, left, right, front, back order
+pub const FACE_NORMALS: [IVec3; 6] = [
+    IVec3::Z,
+    IVec3::NEG_Z,
+    IVec3::NEG_X,
+    IVec3::X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+];
+
+#[derive(Clone, Copy)]
+pub struct Sticker {
+    /// Where the sticker is facing right now
+    pub normal: IVec3,
+    /// Normal of the face the sticker belongs to once solved, used as its colour
+    pub color: IVec3,
+}
+
+#[derive(Clone)]
+pub struct Cubie {
+    pub home: IVec3,
+    pub position: IVec3,
+    pub stickers: Vec<Sticker>,
+    /// Removed pieces are still tracked so the permutations stay complete,
+    /// they just don't show up on the cube
+    pub present: bool,
+}
+
+/// Logical model of the cube, kept in sync with the animated pieces
+#[derive(Clone)]
+pub struct RubikState {
+    pub span: usize,
+    pub cubies: Vec<Cubie>,
+}
+
+/// Number of faces a piece shows when it sits at `piece`
+pub fn exposed_faces(piece: IVec3, span: usize) -> usize {
+    let n = span as i32;
+    piece.to_array().iter().filter(|v| v.abs() == n).count()
+}
+
+/// Rotate `v` clockwise around `axis`, as seen from the tip of `axis`
+pub fn rotate_clockwise(v: IVec3, axis: IVec3, quarter_turns: i32) -> IVec3 {
+    let mut v = v;
+    for _ in 0..quarter_turns.rem_euclid(4) {
+        v = axis * axis.dot(v) - axis.cross(v);
+    }
+    v
+}
+
+impl RubikState {
+    pub fn new(config: &PuzzleConfig) -> Self {
+        let n = config.span as i32;
+        let mut cubies = Vec::new();
+        for z in -n..=n {
+            for y in -n..=n {
+                for x in -n..=n {
+                    let home = IVec3::new(x, y, z);
+                    let stickers = FACE_NORMALS
+                        .iter()
+                        .filter(|normal| home.dot(**normal) == n)
+                        .map(|normal| Sticker {
+                            normal: *normal,
+                            color: *normal,
+                        })
+                        .collect();
+                    cubies.push(Cubie {
+                        home,
+                        position: home,
+                        stickers,
+                        present: config.keeps(home),
+                    });
+                }
+            }
+        }
+        Self {
+            span: config.span,
+            cubies,
+        }
+    }
+    /// Indices of the cubies `turn` moves
+    pub fn layer(&self, turn: Turn) -> Vec<usize> {
+        let Some((axis, lo, hi)) = turn.layers(self.span) else {
+            return Vec::new();
+        };
+        self.cubies
+            .iter()
+            .enumerate()
+            .filter(|(_, cubie)| (lo..=hi).contains(&cubie.position[axis]))
+            .map(|(i, _)| i)
+            .collect()
+    }
+    pub fn apply(&mut self, turn: Turn) {
+        let axis = turn.face.axis();
+        for i in self.layer(turn) {
+            let cubie = &mut self.cubies[i];
+            cubie.position = rotate_clockwise(cubie.position, axis, turn.quarter_turns);
+            for sticker in cubie.stickers.iter_mut() {
+                sticker.normal = rotate_clockwise(sticker.normal, axis, turn.quarter_turns);
+            }
+        }
+    }
+    /// The sticker of a piece still on the cube at `position`, facing `normal`
+    pub fn sticker_at(&self, position: IVec3, normal: IVec3) -> Option<&Sticker> {
+        self.cubies
+            .iter()
+            .filter(|cubie| cubie.present && cubie.position == position)
+            .flat_map(|cubie| cubie.stickers.iter())
+            .find(|sticker| sticker.normal == normal)
+    }
+    /// Every face shows a single colour. Whole cube rotations count as solved,
+    /// a void cube has no centers to tell them apart anyway.
+    pub fn is_solved(&self) -> bool {
+        let mut faces: Vec<(IVec3, IVec3)> = Vec::new();
+        let stickers = self
+            .cubies
+            .iter()
+            .filter(|cubie| cubie.present)
+            .flat_map(|cubie| cubie.stickers.iter());
+        for sticker in stickers {
+            match faces.iter().find(|(normal, _)| *normal == sticker.normal) {
+                Some((_, color)) if *color != sticker.color => return false,
+                Some(_) => {}
+                None => faces.push((sticker.normal, sticker.color)),
+            }
+        }
+        true
+    }
+    /// Corners and middle edges are permuted with different parity. Outer
+    /// turns keep the two in step, a quarter slice turn or whole cube
+    /// rotation such as `M` or `x` sets them apart. A void cube has no
+    /// centers to show that, so a solver has to fix it with an extra slice
+    /// turn.
+    pub fn has_parity(&self) -> bool {
+        let corners = self.permutation_parity(|home| exposed_faces(home, self.span) == 3);
+        let edges = self.permutation_parity(|home| {
+            exposed_faces(home, self.span) == 2 && home.to_array().contains(&0)
+        });
+        corners != edges
+    }
+    /// True when the pieces matching `filter` are in an odd permutation
+    fn permutation_parity<F>(&self, filter: F) -> bool
+    where
+        F: Fn(IVec3) -> bool,
+    {
+        let pieces: Vec<&Cubie> = self
+            .cubies
+            .iter()
+            .filter(|cubie| filter(cubie.home))
+            .collect();
+        let mut visited = vec![false; pieces.len()];
+        let mut odd = false;
+        for start in 0..pieces.len() {
+            let mut i = start;
+            while !visited[i] {
+                visited[i] = true;
+                let Some(next) = pieces
+                    .iter()
+                    .position(|piece| piece.home == pieces[i].position)
+                else {
+                    break;
+                };
+                if !visited[next] {
+                    odd = !odd;
+                }
+                i = next;
+            }
+        }
+        odd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rubik::Move;
+
+    const FACES: [Move; 6] = [
+        Move::Top,
+        Move::Bottom,
+        Move::Left,
+        Move::Right,
+        Move::Front,
+        Move::Back,
+    ];
+
+    /// A fixed run of outer turns, different faces and amounts each step
+    fn outer_scramble(length: usize) -> Vec<Turn> {
+        (0..length)
+            .map(|i| Turn::new(FACES[(i * 5 + i / 6) % 6], 1, (i % 3) as i32 + 1))
+            .collect()
+    }
+
+    #[test]
+    fn new_state_is_solved() {
+        let state = RubikState::new(&PuzzleConfig::new(1));
+        assert!(state.is_solved());
+        assert!(!state.has_parity());
+    }
+
+    #[test]
+    fn outer_turns_undone_are_solved() {
+        let mut state = RubikState::new(&PuzzleConfig::new(1));
+        let turns = outer_scramble(25);
+        for turn in &turns {
+            state.apply(*turn);
+        }
+        assert!(!state.is_solved());
+        for turn in turns.iter().rev() {
+            state.apply(Turn::new(turn.face, turn.depth, -turn.quarter_turns));
+        }
+        assert!(state.is_solved());
+    }
+
+    #[test]
+    fn outer_turns_have_no_parity() {
+        for span in 1..=2 {
+            let mut state = RubikState::new(&PuzzleConfig::new(span));
+            for turn in outer_scramble(40) {
+                state.apply(turn);
+                assert!(!state.has_parity());
+            }
+        }
+    }
+
+    #[test]
+    fn a_slice_turn_has_parity() {
+        // a wide turn and the outer one back leave just the slice turned
+        for (face, quarter_turns) in [(Move::Left, 1), (Move::Bottom, 1), (Move::Front, -1)] {
+            let mut state = RubikState::new(&PuzzleConfig::new(1));
+            state.apply(Turn::new(face, 2, quarter_turns));
+            state.apply(Turn::new(face, 1, -quarter_turns));
+            assert!(!state.is_solved());
+            assert!(state.has_parity());
+        }
+        let mut state = RubikState::new(&PuzzleConfig::new(1));
+        state.apply(Turn::new(Move::Left, 2, 2));
+        state.apply(Turn::new(Move::Left, 1, 2));
+        assert!(!state.has_parity());
+    }
+
+    #[test]
+    fn whole_cube_rotation_is_solved() {
+        let mut state = RubikState::new(&PuzzleConfig::new(1));
+        state.apply(Turn::new(Move::Top, 3, 1));
+        assert!(state.is_solved());
+    }
+}
@@ -0,0 +1,363 @@
+use crate::geometry::rubik_piece::FACE_COLOR_NAMES;
+use crate::rubik::rubik_net::face_color;
+use crate::rubik::rubik_state::{Cubie, FACE_NORMALS, exposed_faces};
+use crate::rubik::{Facelet, PuzzleConfig, Rubik, RubikState};
+use glam::IVec3;
+
+fn color_name(color: IVec3) -> &'static str {
+    FACE_NORMALS
+        .iter()
+        .position(|normal| *normal == color)
+        .map_or("unpainted", |i| FACE_COLOR_NAMES[i])
+}
+
+/// Which of its 3 turns a corner is in, 0 with its top or bottom colour
+/// facing up or down. Turning any layer keeps the sum over all corners
+/// a multiple of 3.
+fn corner_twist(cubie: &Cubie) -> i32 {
+    let Some(sticker) = cubie.stickers.iter().find(|sticker| sticker.color.z != 0) else {
+        return 0;
+    };
+    if sticker.normal.z != 0 {
+        return 0;
+    }
+    let p = cubie.position;
+    let twist = if sticker.normal.x != 0 { 1 } else { 2 };
+    if p.x * p.y * p.z > 0 { twist } else { 3 - twist }
+}
+
+/// An edge is flipped when its top or bottom colour, or its front or back
+/// colour on an edge without one, faces the wrong way for where it sits.
+/// Turning any layer flips an even number of edges.
+fn edge_flipped(cubie: &Cubie) -> bool {
+    let key = cubie
+        .stickers
+        .iter()
+        .find(|sticker| sticker.color.z != 0)
+        .or_else(|| cubie.stickers.iter().find(|sticker| sticker.color.y != 0));
+    let Some(sticker) = key else {
+        return false;
+    };
+    let good = sticker.normal.z != 0 || (cubie.position.z == 0 && sticker.normal.y != 0);
+    !good
+}
+
+/// Whole cube turn read off the center colours: `colors[i]` is the colour
+/// whose center sits on the face `FACE_NORMALS[i]` points at. `None` if no
+/// turn puts them there.
+fn center_turn(colors: [IVec3; 6]) -> Option<CubeTurn> {
+    let [z, _, _, x, y, _] = colors;
+    let turn = CubeTurn { x, y, z };
+    // a mirror image isn't a turn, even with every pair of opposite colours
+    // on opposite faces
+    let fits = x.cross(y) == z
+        && FACE_NORMALS
+            .iter()
+            .zip(colors)
+            .all(|(normal, color)| turn.apply(*normal) == color);
+    fits.then_some(turn)
+}
+
+/// A whole cube turn, by where it takes the x, y and z axes
+struct CubeTurn {
+    x: IVec3,
+    y: IVec3,
+    z: IVec3,
+}
+
+impl CubeTurn {
+    fn apply(&self, v: IVec3) -> IVec3 {
+        self.x * v.x + self.y * v.y + self.z * v.z
+    }
+    fn undo(&self, v: IVec3) -> IVec3 {
+        IVec3::new(self.x.dot(v), self.y.dot(v), self.z.dot(v))
+    }
+}
+
+/// Colours painted on a cube's stickers one by one, to copy a real cube and
+/// load it as a state
+pub struct StickerPaint {
+    /// Colour painted on a click, as the normal of the face it belongs to
+    pub brush: IVec3,
+    span: usize,
+    /// Colour of every facelet in `Facelet::all` order, zero where nothing
+    /// is painted yet
+    colors: Vec<IVec3>,
+    /// Whether the cube turned by itself before painting, given back to it
+    /// when painting ends
+    pub auto_move: bool,
+}
+
+impl StickerPaint {
+    /// Start from what `rubik` shows now. Only a whole 3x3x3 with its face
+    /// colours can be painted.
+    pub fn new(rubik: &Rubik) -> Result<Self, String> {
+        let mut paint = Self::from_state(&rubik.state, &rubik.config)?;
+        paint.auto_move = rubik.auto_move;
+        Ok(paint)
+    }
+    fn from_state(state: &RubikState, config: &PuzzleConfig) -> Result<Self, String> {
+        if state.span != 1 {
+            return Err(String::from("Only the 3x3x3 can be painted"));
+        }
+        if config.sticker_color.is_some() {
+            return Err(String::from("Every sticker of this cube has the same colour"));
+        }
+        let missing = state
+            .cubies
+            .iter()
+            .any(|cubie| !cubie.present && exposed_faces(cubie.home, state.span) > 0);
+        if missing {
+            return Err(String::from("Every piece has to be on the cube"));
+        }
+        let colors = Facelet::all(state.span)
+            .iter()
+            .map(|facelet| {
+                state
+                    .sticker_at(facelet.position(state.span), facelet.normal())
+                    .map_or(IVec3::ZERO, |sticker| sticker.color)
+            })
+            .collect();
+        Ok(Self {
+            brush: IVec3::Z,
+            span: state.span,
+            colors,
+            auto_move: false,
+        })
+    }
+    pub fn color(&self, facelet: &Facelet) -> u32 {
+        face_color(self.colors[facelet.index(self.span)])
+    }
+    fn is_center(&self, facelet: &Facelet) -> bool {
+        facelet.row == self.span && facelet.column == self.span
+    }
+    /// Paint `facelet` with the brush. Returns false if nothing changed.
+    pub fn paint(&mut self, facelet: &Facelet) -> bool {
+        let color = &mut self.colors[facelet.index(self.span)];
+        let changed = *color != self.brush;
+        *color = self.brush;
+        changed
+    }
+    /// Wipe every sticker but the centers
+    pub fn clear(&mut self) {
+        for facelet in Facelet::all(self.span) {
+            if !self.is_center(&facelet) {
+                self.colors[facelet.index(self.span)] = IVec3::ZERO;
+            }
+        }
+    }
+    /// The state of the painted cube, or what stops it from being one that
+    /// turning a solved cube could reach
+    pub fn state(&self, config: &PuzzleConfig) -> Result<RubikState, String> {
+        let span = self.span;
+        let unpainted = self.colors.iter().filter(|c| **c == IVec3::ZERO).count();
+        if unpainted > 0 {
+            return Err(format!("{unpainted} stickers are still to paint"));
+        }
+        let per_face = (span * 2 + 1).pow(2);
+        for color in FACE_NORMALS {
+            let count = self.colors.iter().filter(|c| **c == color).count();
+            if count != per_face {
+                return Err(format!(
+                    "There are {count} {} stickers instead of {per_face}",
+                    color_name(color)
+                ));
+            }
+        }
+        let facelets = Facelet::all(span);
+        let centers = FACE_NORMALS.map(|normal| {
+            let center = facelets
+                .iter()
+                .find(|facelet| self.is_center(facelet) && facelet.normal() == normal);
+            center.map_or(IVec3::ZERO, |facelet| self.colors[facelet.index(span)])
+        });
+        // slices and rotations move the centers, so the pieces are checked
+        // on the cube turned back to its centers' homes
+        let Some(turn) = center_turn(centers) else {
+            return Err(String::from("No cube has its centers like that"));
+        };
+        // position, normal and colour of every sticker on the turned back cube
+        let stickers: Vec<(IVec3, IVec3, IVec3)> = facelets
+            .iter()
+            .map(|facelet| {
+                let position = turn.apply(facelet.position(span));
+                (position, turn.apply(facelet.normal()), self.colors[facelet.index(span)])
+            })
+            .collect();
+        let mut state = RubikState::new(config);
+        let mut taken = vec![false; state.cubies.len()];
+        let mut placed = Vec::new();
+        let places = state
+            .cubies
+            .iter()
+            .map(|cubie| cubie.home)
+            .filter(|home| exposed_faces(*home, span) > 0);
+        for position in places {
+            // normal and colour of every sticker painted at this place
+            let painted: Vec<(IVec3, IVec3)> = stickers
+                .iter()
+                .filter(|(at, _, _)| *at == position)
+                .map(|(_, normal, color)| (*normal, *color))
+                .collect();
+            let twice = painted.iter().enumerate().find_map(|(i, (_, color))| {
+                painted[..i].iter().any(|(_, c)| c == color).then_some(*color)
+            });
+            if let Some(color) = twice {
+                return Err(format!("A piece has two {} stickers", color_name(color)));
+            }
+            // the colours are all different, so a piece with as many
+            // stickers and every painted colour has exactly those colours
+            let matches = |cubie: &Cubie| {
+                cubie.stickers.len() == painted.len()
+                    && painted
+                        .iter()
+                        .all(|(_, color)| cubie.stickers.iter().any(|s| s.color == *color))
+            };
+            let Some(index) = (0..state.cubies.len()).find(|i| !taken[*i] && matches(&state.cubies[*i]))
+            else {
+                let names: Vec<&str> = painted.iter().map(|(_, color)| color_name(*color)).collect();
+                return Err(match state.cubies.iter().any(matches) {
+                    true => format!("There are two {} pieces", names.join(" and ")),
+                    false => format!("No piece is {}", names.join(" and ")),
+                });
+            };
+            taken[index] = true;
+            placed.push((index, position, painted));
+        }
+        for (index, position, painted) in placed {
+            let cubie = &mut state.cubies[index];
+            cubie.position = position;
+            for sticker in cubie.stickers.iter_mut() {
+                if let Some((normal, _)) = painted.iter().find(|(_, color)| *color == sticker.color) {
+                    sticker.normal = *normal;
+                }
+            }
+            if let [a, b, c] = cubie.stickers.as_slice() {
+                // a real corner's colours can't go round the other way
+                let turned = a.normal.cross(b.normal).dot(c.normal);
+                let solved = a.color.cross(b.color).dot(c.color);
+                if turned != solved {
+                    let names: Vec<&str> = [a, b, c].iter().map(|s| color_name(s.color)).collect();
+                    return Err(format!(
+                        "The {} corner has its colours the wrong way round",
+                        names.join(", ")
+                    ));
+                }
+            }
+        }
+        let corners = state
+            .cubies
+            .iter()
+            .filter(|cubie| exposed_faces(cubie.home, span) == 3);
+        if corners.map(corner_twist).sum::<i32>() % 3 != 0 {
+            return Err(String::from("A corner is twisted"));
+        }
+        let edges = state
+            .cubies
+            .iter()
+            .filter(|cubie| exposed_faces(cubie.home, span) == 2);
+        if edges.filter(|cubie| edge_flipped(cubie)).count() % 2 != 0 {
+            return Err(String::from("An edge is flipped"));
+        }
+        if state.has_parity() {
+            return Err(String::from("Two pieces are swapped"));
+        }
+        for cubie in state.cubies.iter_mut() {
+            cubie.position = turn.undo(cubie.position);
+            for sticker in cubie.stickers.iter_mut() {
+                sticker.normal = turn.undo(sticker.normal);
+            }
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rubik::Turn;
+
+    const SCRAMBLE: &str = "R U' M F2 E' L S x D2 y' B z2 R' M2 U";
+
+    fn scrambled() -> (RubikState, PuzzleConfig) {
+        let config = PuzzleConfig::new(1);
+        let mut state = RubikState::new(&config);
+        for turn in Turn::parse_sequence(SCRAMBLE, 1).unwrap() {
+            state.apply(turn);
+        }
+        (state, config)
+    }
+
+    /// Index in `colors` of the sticker facing `normal` at `position`
+    fn at(position: IVec3, normal: IVec3) -> usize {
+        Facelet::from_sticker(position, normal, 1).unwrap().index(1)
+    }
+
+    #[test]
+    fn painted_scramble_round_trips() {
+        let (state, config) = scrambled();
+        let paint = StickerPaint::from_state(&state, &config).unwrap();
+        let painted = paint.state(&config).unwrap();
+        for facelet in Facelet::all(1) {
+            let (position, normal) = (facelet.position(1), facelet.normal());
+            let color = |state: &RubikState| state.sticker_at(position, normal).unwrap().color;
+            assert_eq!(color(&painted), color(&state));
+        }
+        let again = StickerPaint::from_state(&painted, &config).unwrap();
+        assert_eq!(again.colors, paint.colors);
+    }
+
+    #[test]
+    fn unreachable_cubes_are_refused() {
+        let (state, config) = scrambled();
+        let scrambled = StickerPaint::from_state(&state, &config).unwrap();
+        let corner = [IVec3::Z, IVec3::Y, IVec3::X].map(|normal| at(IVec3::ONE, normal));
+        let edge = [IVec3::Z, IVec3::Y].map(|normal| at(IVec3::new(0, 1, 1), normal));
+        let other = [IVec3::Z, IVec3::X].map(|normal| at(IVec3::new(1, 0, 1), normal));
+
+        let mut twisted = scrambled.colors.clone();
+        twisted[corner[0]] = scrambled.colors[corner[2]];
+        twisted[corner[1]] = scrambled.colors[corner[0]];
+        twisted[corner[2]] = scrambled.colors[corner[1]];
+
+        let mut flipped = scrambled.colors.clone();
+        flipped.swap(edge[0], edge[1]);
+
+        let mut swapped = scrambled.colors.clone();
+        swapped.swap(edge[0], other[0]);
+        swapped.swap(edge[1], other[1]);
+
+        let cases = [
+            (twisted, "A corner is twisted"),
+            (flipped, "An edge is flipped"),
+            (swapped, "Two pieces are swapped"),
+        ];
+        for (colors, error) in cases {
+            let paint = StickerPaint { colors, ..StickerPaint::from_state(&state, &config).unwrap() };
+            assert_eq!(paint.state(&config).err().as_deref(), Some(error));
+        }
+    }
+
+    #[test]
+    fn a_piece_with_a_colour_twice_is_refused() {
+        let config = PuzzleConfig::new(1);
+        let mut paint = StickerPaint::from_state(&RubikState::new(&config), &config).unwrap();
+        // the top left front corner gets a second top sticker, and a top
+        // edge takes its left one so every colour still shows 9 times
+        paint.colors[at(IVec3::new(-1, -1, 1), IVec3::NEG_X)] = IVec3::Z;
+        paint.colors[at(IVec3::new(1, 0, 1), IVec3::Z)] = IVec3::NEG_X;
+        assert_eq!(
+            paint.state(&config).err().as_deref(),
+            Some(format!("A piece has two {} stickers", color_name(IVec3::Z)).as_str())
+        );
+    }
+
+    #[test]
+    fn painting_remembers_whether_the_cube_turned_by_itself() {
+        for auto_move in [false, true] {
+            let mut rubik = Rubik::headless(PuzzleConfig::new(1));
+            rubik.auto_move = auto_move;
+            assert_eq!(StickerPaint::new(&rubik).unwrap().auto_move, auto_move);
+        }
+    }
+}
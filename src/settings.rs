@@ -0,0 +1,43 @@
+/// Settings kept between runs, as text under a short name: a file in the
+/// `settings` folder on native, a localStorage entry on the web
+#[cfg(not(target_arch = "wasm32"))]
+const SETTINGS_DIR: &str = "settings";
+/// Prefix of the localStorage keys, the page may share its origin
+#[cfg(target_arch = "wasm32")]
+const STORAGE_PREFIX: &str = "rubik.";
+
+/// Text saved under `name`, `None` if nothing was saved yet
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(name: &str) -> Option<String> {
+    let path = std::path::Path::new(SETTINGS_DIR).join(format!("{name}.ron"));
+    std::fs::read_to_string(path).ok()
+}
+
+/// Keep `text` under `name`, logging rather than failing when it can't be
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(name: &str, text: &str) {
+    let path = std::path::Path::new(SETTINGS_DIR).join(format!("{name}.ron"));
+    let saved = std::fs::create_dir_all(SETTINGS_DIR).and_then(|_| std::fs::write(&path, text));
+    match saved {
+        Ok(()) => log::info!("saved {}", path.display()),
+        Err(e) => log::error!("failed to save {}: {e}", path.display()),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(name: &str) -> Option<String> {
+    storage()?.get_item(&format!("{STORAGE_PREFIX}{name}")).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(name: &str, text: &str) {
+    let saved = storage().map(|storage| storage.set_item(&format!("{STORAGE_PREFIX}{name}"), text));
+    if !matches!(saved, Some(Ok(()))) {
+        log::error!("failed to save {name} to local storage");
+    }
+}
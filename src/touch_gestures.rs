@@ -0,0 +1,81 @@
+use glam::Vec2;
+use winit::event::{Touch, TouchPhase};
+
+/// What the fingers on the screen are doing
+#[derive(Clone, Copy, Debug)]
+pub enum Gesture {
+    /// A single finger went down
+    Press(Vec2),
+    /// The single finger moved by `delta` to `position`
+    Drag { position: Vec2, delta: Vec2 },
+    /// The single finger was lifted, or a second one joined it
+    Release,
+    /// Two fingers moved, `scale` is how much further apart they are and
+    /// `rotation` how far they turned clockwise on screen, in radians
+    Pinch { scale: f32, rotation: f32 },
+}
+
+/// Turns raw touch events into gestures. Once a second finger lands, the
+/// fingers left don't drag until every one of them has been lifted.
+pub struct TouchGestures {
+    touches: Vec<(u64, Vec2)>,
+    multi: bool,
+}
+
+impl TouchGestures {
+    pub fn new() -> Self {
+        Self {
+            touches: Vec::new(),
+            multi: false,
+        }
+    }
+    pub fn touch(&mut self, touch: &Touch) -> Option<Gesture> {
+        let position = Vec2::new(touch.location.x as f32, touch.location.y as f32);
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.push((touch.id, position));
+                match self.touches.len() {
+                    1 if !self.multi => Some(Gesture::Press(position)),
+                    2 => {
+                        self.multi = true;
+                        Some(Gesture::Release)
+                    }
+                    _ => None,
+                }
+            }
+            TouchPhase::Moved => {
+                let before = self.touches.clone();
+                let i = self.touches.iter().position(|(id, _)| *id == touch.id)?;
+                self.touches[i].1 = position;
+                match (before.as_slice(), self.touches.as_slice()) {
+                    ([(_, from)], [_]) if !self.multi => Some(Gesture::Drag {
+                        position,
+                        delta: position - *from,
+                    }),
+                    ([(_, a0), (_, b0)], [(_, a1), (_, b1)]) => {
+                        let (from, to) = (*b0 - *a0, *b1 - *a1);
+                        if from.length() <= 0.0 {
+                            return None;
+                        }
+                        Some(Gesture::Pinch {
+                            scale: to.length() / from.length(),
+                            // screen y points down, so this angle grows clockwise
+                            rotation: from.angle_to(to),
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let count = self.touches.len();
+                self.touches.retain(|(id, _)| *id != touch.id);
+                let gesture = (count == 1 && self.touches.is_empty() && !self.multi)
+                    .then_some(Gesture::Release);
+                if self.touches.is_empty() {
+                    self.multi = false;
+                }
+                gesture
+            }
+        }
+    }
+}
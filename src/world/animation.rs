@@ -0,0 +1,358 @@
+use crate::world::node::Variant;
+use crate::world::{Easing, Node, NodeRef};
+use glam::{Quat, Vec3};
+use wgpu::Color;
+
+/// What happens once an animation reaches its last keyframe
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LoopMode {
+    /// Stop on the last keyframe
+    Once,
+    /// Jump back to the first keyframe
+    Repeat,
+    /// Play backwards to the first keyframe, then forwards again
+    PingPong,
+}
+
+/// Values a track can blend between
+pub trait Interpolate: Copy {
+    fn interpolate(self, other: Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Vec3 {
+    fn interpolate(self, other: Self, t: f32) -> Self {
+        self.lerp(other, t)
+    }
+}
+
+impl Interpolate for Quat {
+    fn interpolate(self, other: Self, t: f32) -> Self {
+        self.slerp(other, t)
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(self, other: Self, t: f32) -> Self {
+        let t = t as f64;
+        Color {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Keyframe<T> {
+    /// Seconds from the start of the track
+    pub time: f32,
+    pub value: T,
+    /// Curve used to get here from the previous keyframe
+    pub easing: Easing,
+}
+
+/// Keyframes of a single value, sorted by time
+#[derive(Clone)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Interpolate> Track<T> {
+    /// A track holding `value` until more keyframes are added
+    pub fn new(value: T) -> Self {
+        Self {
+            keyframes: vec![Keyframe {
+                time: 0.0,
+                value,
+                easing: Easing::Linear,
+            }],
+        }
+    }
+    /// Go from `from` to `to` in `duration` seconds
+    pub fn tween(from: T, to: T, duration: f32, easing: Easing) -> Self {
+        Self::new(from).key(duration, to, easing)
+    }
+    /// Add a keyframe reaching `value` at `time`, eased with `easing`
+    pub fn key(mut self, time: f32, value: T, easing: Easing) -> Self {
+        let i = self.keyframes.partition_point(|k| k.time <= time);
+        self.keyframes.insert(i, Keyframe { time, value, easing });
+        self
+    }
+    /// Time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+    /// Value at `time`, holding the first and last keyframes outside the track
+    pub fn sample(&self, time: f32) -> T {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return self.keyframes[0].value;
+        }
+        if next == self.keyframes.len() {
+            return self.keyframes[next - 1].value;
+        }
+        let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        let t = (time - a.time) / (b.time - a.time);
+        a.value.interpolate(b.value, b.easing.ease(t))
+    }
+}
+
+/// A track with its own clock, for values read by hand rather than bound
+/// to a node
+pub struct Clip<T> {
+    pub track: Track<T>,
+    time: f32,
+}
+
+impl<T: Interpolate> Clip<T> {
+    pub fn new(track: Track<T>) -> Self {
+        Self { track, time: 0.0 }
+    }
+    pub fn duration(&self) -> f32 {
+        self.track.duration()
+    }
+    pub fn value(&self) -> T {
+        self.track.sample(self.time)
+    }
+    pub fn move_to(&mut self, time: f32) -> T {
+        self.time = time.clamp(0.0, self.duration());
+        self.value()
+    }
+    pub fn move_by(&mut self, delta_time: f32) -> T {
+        self.move_to(self.time + delta_time)
+    }
+    pub fn is_finished(&self) -> bool {
+        self.time >= self.duration()
+    }
+}
+
+/// Keyframe tracks driving the properties of a node
+pub struct Animation {
+    target: NodeRef,
+    pub translation: Option<Track<Vec3>>,
+    pub rotation: Option<Track<Quat>>,
+    pub scale: Option<Track<Vec3>>,
+    /// Only used when the target is a light
+    pub color: Option<Track<Color>>,
+    pub radius: Option<Track<f32>>,
+    pub loop_mode: LoopMode,
+    /// 1.0 plays the tracks at the times they were keyed with
+    pub speed: f32,
+    time: f32,
+}
+
+impl Animation {
+    pub fn new(target: NodeRef, loop_mode: LoopMode) -> Self {
+        Self {
+            target,
+            translation: None,
+            rotation: None,
+            scale: None,
+            color: None,
+            radius: None,
+            loop_mode,
+            speed: 1.0,
+            time: 0.0,
+        }
+    }
+    pub fn with_translation(mut self, track: Track<Vec3>) -> Self {
+        self.translation = Some(track);
+        self
+    }
+    pub fn with_rotation(mut self, track: Track<Quat>) -> Self {
+        self.rotation = Some(track);
+        self
+    }
+    pub fn with_scale(mut self, track: Track<Vec3>) -> Self {
+        self.scale = Some(track);
+        self
+    }
+    pub fn with_color(mut self, track: Track<Color>) -> Self {
+        self.color = Some(track);
+        self
+    }
+    pub fn with_radius(mut self, track: Track<f32>) -> Self {
+        self.radius = Some(track);
+        self
+    }
+    /// Time of the last keyframe over every track
+    pub fn duration(&self) -> f32 {
+        [
+            self.translation.as_ref().map(Track::duration),
+            self.rotation.as_ref().map(Track::duration),
+            self.scale.as_ref().map(Track::duration),
+            self.color.as_ref().map(Track::duration),
+            self.radius.as_ref().map(Track::duration),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(0.0, f32::max)
+    }
+    pub fn is_finished(&self) -> bool {
+        self.loop_mode == LoopMode::Once && self.time >= self.duration()
+    }
+    /// Time within the tracks once the loop mode is applied
+    fn local_time(&self) -> f32 {
+        let duration = self.duration();
+        if duration <= 0.0 {
+            return 0.0;
+        }
+        match self.loop_mode {
+            LoopMode::Once => self.time.min(duration),
+            LoopMode::Repeat => self.time.rem_euclid(duration),
+            LoopMode::PingPong => {
+                let t = self.time.rem_euclid(duration * 2.0);
+                if t > duration { duration * 2.0 - t } else { t }
+            }
+        }
+    }
+    /// Jump to `time` seconds from the start and update the target
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.max(0.0);
+        self.apply();
+    }
+    pub fn update(&mut self, delta_time: f32) {
+        self.seek(self.time + delta_time * self.speed);
+    }
+    fn apply(&self) {
+        let time = self.local_time();
+        let mut node = self.target.borrow_mut();
+        if let Some(track) = &self.translation {
+            let t = track.sample(time);
+            node.translate(t.x, t.y, t.z);
+        }
+        if let Some(track) = &self.rotation {
+            node.rotate_quat(track.sample(time));
+        }
+        if let Some(track) = &self.scale {
+            let s = track.sample(time);
+            node.scale(s.x, s.y, s.z);
+        }
+        if let Variant::Light(color, radius) = &mut node.variant {
+            if let Some(track) = &self.color {
+                *color = track.sample(time);
+            }
+            if let Some(track) = &self.radius {
+                *radius = track.sample(time);
+            }
+        }
+    }
+}
+
+/// Plays a set of animations together, dropping those that are done
+pub struct Animator {
+    animations: Vec<Animation>,
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Self {
+            animations: Vec::new(),
+        }
+    }
+    pub fn add(&mut self, animation: Animation) {
+        self.animations.push(animation);
+    }
+    /// Jump every animation to `time` seconds from its start
+    pub fn seek(&mut self, time: f32) {
+        for animation in self.animations.iter_mut() {
+            animation.seek(time);
+        }
+    }
+    pub fn update(&mut self, delta_time: f32) {
+        for animation in self.animations.iter_mut() {
+            animation.update(delta_time);
+        }
+        self.animations.retain(|animation| !animation.is_finished());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::new_group;
+
+    fn ramp() -> Track<f32> {
+        Track::new(0.0).key(1.0, 10.0, Easing::Linear).key(3.0, 30.0, Easing::Linear)
+    }
+
+    #[test]
+    fn sample_blends_between_keyframes() {
+        let track = ramp();
+        assert_eq!(track.duration(), 3.0);
+        assert_eq!(track.sample(0.0), 0.0);
+        assert_eq!(track.sample(0.5), 5.0);
+        assert_eq!(track.sample(1.0), 10.0);
+        assert_eq!(track.sample(2.0), 20.0);
+        assert_eq!(track.sample(3.0), 30.0);
+    }
+
+    #[test]
+    fn sample_holds_outside_the_track() {
+        let track = ramp();
+        assert_eq!(track.sample(-1.0), 0.0);
+        assert_eq!(track.sample(5.0), 30.0);
+        assert_eq!(Track::new(7.0).sample(2.0), 7.0);
+    }
+
+    #[test]
+    fn keys_stay_sorted_and_use_their_easing() {
+        let track = Track::new(0.0)
+            .key(2.0, 2.0, Easing::Linear)
+            .key(1.0, 4.0, Easing::QuadOut);
+        assert_eq!(track.sample(1.0), 4.0);
+        assert_eq!(track.sample(1.5), 3.0);
+        let eased = track.sample(0.5);
+        assert!((eased - 4.0 * Easing::QuadOut.ease(0.5)).abs() < 1e-6);
+    }
+
+    /// Time the animation reads its tracks at after playing `time` seconds
+    fn local_time(loop_mode: LoopMode, time: f32) -> f32 {
+        let mut animation = Animation::new(new_group(), loop_mode).with_radius(ramp());
+        animation.seek(time);
+        animation.local_time()
+    }
+
+    #[test]
+    fn loop_modes() {
+        assert_eq!(local_time(LoopMode::Once, 2.0), 2.0);
+        assert_eq!(local_time(LoopMode::Once, 5.0), 3.0);
+        assert_eq!(local_time(LoopMode::Repeat, 2.0), 2.0);
+        assert_eq!(local_time(LoopMode::Repeat, 4.0), 1.0);
+        assert_eq!(local_time(LoopMode::Repeat, 6.5), 0.5);
+        assert_eq!(local_time(LoopMode::PingPong, 2.0), 2.0);
+        assert_eq!(local_time(LoopMode::PingPong, 4.0), 2.0);
+        assert_eq!(local_time(LoopMode::PingPong, 6.5), 0.5);
+    }
+
+    #[test]
+    fn only_once_finishes() {
+        for (loop_mode, finished) in [
+            (LoopMode::Once, true),
+            (LoopMode::Repeat, false),
+            (LoopMode::PingPong, false),
+        ] {
+            let mut animation = Animation::new(new_group(), loop_mode).with_radius(ramp());
+            animation.update(2.0);
+            assert!(!animation.is_finished());
+            animation.update(2.0);
+            assert_eq!(animation.is_finished(), finished);
+        }
+    }
+
+    #[test]
+    fn animation_moves_its_target() {
+        let node = new_group();
+        let track = Track::tween(Vec3::ZERO, Vec3::new(2.0, 4.0, 6.0), 2.0, Easing::Linear);
+        let mut animation = Animation::new(node.clone(), LoopMode::Once).with_translation(track);
+        animation.seek(1.0);
+        assert_eq!(node.borrow().translation, Vec3::new(1.0, 2.0, 3.0));
+    }
+}
@@ -1,51 +1,383 @@
-use glam::{Mat4, Vec3};
+use crate::world::{Clip, Easing, Ray, Track, LAYER_ALL};
+use glam::{Mat4, Quat, Vec2, Vec3};
 use std::f32::consts;
 
+/// Radians the view turns per pixel dragged
+const DRAG_SPEED: f32 = 0.01;
+/// Seconds a move to a preset view takes
+const VIEW_DURATION: f32 = 0.6;
+/// Below this many pixels per second a released drag stops spinning
+const MIN_SPIN: f32 = 1.0;
+/// Room left around a framed subject, leaving space for it to spin in
+const FRAME_MARGIN: f32 = 1.8;
+
+/// How dragging turns the view
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CameraMode {
+    /// Around the vertical axis and up or down, stopping short of the poles
+    Orbit,
+    /// Around whichever axis the drag points at, rolling over the top freely
+    Arcball,
+}
+
+/// Views the camera can fly to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CameraView {
+    Front,
+    Top,
+    Isometric,
+}
+
+impl CameraView {
+    pub const ALL: [CameraView; 3] = [CameraView::Front, CameraView::Top, CameraView::Isometric];
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraView::Front => "Front",
+            CameraView::Top => "Top",
+            CameraView::Isometric => "Isometric",
+        }
+    }
+    /// Azimuth and elevation of the view
+    fn angles(&self) -> (f32, f32) {
+        match self {
+            CameraView::Front => (0.0, 0.0),
+            CameraView::Top => (0.0, consts::FRAC_PI_2),
+            CameraView::Isometric => (consts::FRAC_PI_4, (1.0 / 2.0_f32.sqrt()).atan()),
+        }
+    }
+}
+
+/// How the view is flattened onto the screen
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Projection {
+    /// Far things look smaller
+    Perspective,
+    /// Parallel lines stay parallel, showing as much as a perspective view
+    /// does at the target
+    Orthographic,
+}
+
+/// Flight to a new view
+struct Transition {
+    orientation: Clip<Quat>,
+    target: Clip<Vec3>,
+    distance: Clip<f32>,
+}
+
 pub struct Camera {
     pub distance: f32,
     pub azimuth: f32,  // Horizontal angle
     pub elevation: f32, // Vertical angle
     pub target: Vec3,
     pub fov: f32,
+    pub projection: Projection,
+    /// Clip planes, fitted to the scene every frame by the renderer
+    pub near: f32,
+    pub far: f32,
+    /// Render layers drawn by this camera, entities on none of them are skipped
+    pub layers: u32,
+    mode: CameraMode,
+    /// Where the eye sits around `target`, looking down -Z. Kept in step with
+    /// the angles, it is what the arcball turns.
+    orientation: Quat,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Distance showing the whole of what was last framed, 0 before that
+    framing: f32,
+    /// Keep spinning after a drag is let go
+    pub inertia: bool,
+    /// How fast the spin dies down, per second
+    pub damping: f32,
+    dragging: bool,
+    /// Pixels dragged since the last update
+    dragged: Vec2,
+    /// Pixels per second the view spins at
+    spin: Vec2,
+    transition: Option<Transition>,
+}
+
+/// Orientation of an eye at `azimuth` and `elevation`, with no roll
+fn orbit_orientation(azimuth: f32, elevation: f32) -> Quat {
+    Quat::from_rotation_y(azimuth) * Quat::from_rotation_x(-elevation)
 }
 
 impl Camera {
     pub fn new() -> Self {
+        let (azimuth, elevation) = (0.25, 0.5);
         Self {
             distance: 30.0,
-            azimuth: 0.25,
-            elevation: 0.5,
+            azimuth,
+            elevation,
             target: Vec3::ZERO,
             fov: consts::FRAC_PI_4,
+            projection: Projection::Perspective,
+            near: 1.0,
+            far: 1000.0,
+            layers: LAYER_ALL,
+            mode: CameraMode::Orbit,
+            orientation: orbit_orientation(azimuth, elevation),
+            min_distance: 10.0,
+            max_distance: 100.0,
+            framing: 0.0,
+            inertia: true,
+            damping: 4.0,
+            dragging: false,
+            dragged: Vec2::ZERO,
+            spin: Vec2::ZERO,
+            transition: None,
         }
     }
     
     pub fn orbit(&mut self, delta_azimuth: f32, delta_elevation: f32) {
         self.azimuth += delta_azimuth;
         self.elevation = (self.elevation + delta_elevation).clamp(-1.5, 1.5);
+        self.orientation = orbit_orientation(self.azimuth, self.elevation);
+    }
+
+    /// Jump to `distance`, `azimuth` and `elevation`, whatever the mode
+    pub fn set_orbit(&mut self, distance: f32, azimuth: f32, elevation: f32) {
+        self.distance = distance;
+        self.azimuth = azimuth;
+        self.elevation = 0.0;
+        self.orbit(0.0, elevation);
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Switch how dragging turns the view, an orbit drops any roll
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+        self.set_orientation(self.orientation);
+    }
+
+    fn set_orientation(&mut self, orientation: Quat) {
+        let eye = orientation * Vec3::Z;
+        self.azimuth = eye.x.atan2(eye.z);
+        self.elevation = eye.y.clamp(-1.0, 1.0).asin();
+        self.orientation = match self.mode {
+            CameraMode::Orbit => {
+                self.elevation = self.elevation.clamp(-1.5, 1.5);
+                orbit_orientation(self.azimuth, self.elevation)
+            }
+            CameraMode::Arcball => orientation.normalize(),
+        };
+    }
+
+    /// Turn the view by a drag of `delta` pixels
+    fn turn(&mut self, delta: Vec2) {
+        match self.mode {
+            CameraMode::Orbit => self.orbit(-delta.x * DRAG_SPEED, delta.y * DRAG_SPEED),
+            CameraMode::Arcball => {
+                // the eye moves the opposite way to the scene under the cursor
+                let rotation = Quat::from_scaled_axis(Vec3::new(-delta.y, -delta.x, 0.0) * DRAG_SPEED);
+                self.set_orientation(self.orientation * rotation);
+            }
+        }
+    }
+
+    /// A drag on the view starts, stopping any spin or flight
+    pub fn grab(&mut self) {
+        self.dragging = true;
+        self.dragged = Vec2::ZERO;
+        self.spin = Vec2::ZERO;
+        self.transition = None;
+    }
+
+    /// Turn the view by a drag of `delta` pixels
+    pub fn drag(&mut self, delta: Vec2) {
+        self.turn(delta);
+        self.dragged += delta;
+    }
+
+    /// The drag is let go, the view carries on spinning if `inertia` is set
+    pub fn release(&mut self) {
+        self.dragging = false;
+        if !self.inertia {
+            self.spin = Vec2::ZERO;
+        }
+    }
+
+    /// Slide `target` across the view by a drag of `delta` pixels on a
+    /// window `height` pixels high, so the point under the cursor stays there
+    pub fn pan(&mut self, delta: Vec2, height: f32) {
+        self.transition = None;
+        let units_per_pixel = 2.0 * self.distance * (self.fov * 0.5).tan() / height;
+        let forward = (self.target - self.get_eye_position()).normalize();
+        let right = self.orientation * Vec3::X;
+        let up = right.cross(forward);
+        self.target += (up * delta.y - right * delta.x) * units_per_pixel;
     }
     
     pub fn zoom(&mut self, delta: f32) {
-        self.distance = (self.distance + delta).clamp(10.0, 100.0);
+        self.distance = (self.distance + delta).clamp(self.min_distance, self.max_distance);
+    }
+
+    /// Set the distance and zoom limits for a sphere of `radius` around the
+    /// target to fit a view of `aspect_ratio`, whichever side is narrower.
+    /// A view zoomed in or out keeps that zoom relative to the new fit.
+    pub fn frame(&mut self, radius: f32, aspect_ratio: f32) {
+        let half_vertical = self.fov * 0.5;
+        let half_horizontal = (half_vertical.tan() * aspect_ratio).atan();
+        let fitting = radius / half_vertical.min(half_horizontal).sin() * FRAME_MARGIN;
+        let zoom = if self.framing > 0.0 {
+            self.distance / self.framing
+        } else {
+            1.0
+        };
+        self.framing = fitting;
+        self.min_distance = (radius * 1.2).max(fitting * 0.35);
+        self.max_distance = fitting * 4.0;
+        self.distance = (fitting * zoom).clamp(self.min_distance, self.max_distance);
+    }
+
+    /// Distance showing the whole of what was last framed
+    pub fn framing_distance(&self) -> f32 {
+        if self.framing > 0.0 {
+            self.framing
+        } else {
+            Camera::new().distance
+        }
+    }
+
+    /// Put the clip planes just around a sphere at `center`, so nothing in
+    /// it is cut off and the depth buffer isn't spread over empty space
+    pub fn fit_clip_planes(&mut self, center: Vec3, radius: f32) {
+        let distance = self.get_eye_position().distance(center);
+        self.far = (distance + radius).max(1.0);
+        self.near = (distance - radius).max(self.far * 0.001);
+    }
+
+    /// Fly to `view`, keeping the distance
+    pub fn show_view(&mut self, view: CameraView) {
+        let (azimuth, elevation) = view.angles();
+        self.fly_to(orbit_orientation(azimuth, elevation), self.target, self.distance);
+    }
+
+    /// Fly back to the view the camera started with
+    pub fn reset(&mut self) {
+        let start = Camera::new();
+        self.fly_to(start.orientation, start.target, self.framing_distance());
+    }
+
+    fn fly_to(&mut self, orientation: Quat, target: Vec3, distance: f32) {
+        self.spin = Vec2::ZERO;
+        let easing = Easing::SineInOut;
+        self.transition = Some(Transition {
+            orientation: Clip::new(Track::tween(self.orientation, orientation, VIEW_DURATION, easing)),
+            target: Clip::new(Track::tween(self.target, target, VIEW_DURATION, easing)),
+            distance: Clip::new(Track::tween(self.distance, distance, VIEW_DURATION, easing)),
+        });
+    }
+
+    /// Carry on spinning after a drag, and flying to a view
+    pub fn update(&mut self, delta_time: f32) {
+        if delta_time <= 0.0 {
+            return;
+        }
+        if self.dragging {
+            self.spin = self.dragged / delta_time;
+            self.dragged = Vec2::ZERO;
+        } else if self.spin.length() > MIN_SPIN {
+            self.turn(self.spin * delta_time);
+            self.spin *= (-self.damping * delta_time).exp();
+        } else {
+            self.spin = Vec2::ZERO;
+        }
+        if let Some(transition) = self.transition.as_mut() {
+            let orientation = transition.orientation.move_by(delta_time);
+            self.target = transition.target.move_by(delta_time);
+            self.distance = transition.distance.move_by(delta_time);
+            if transition.orientation.is_finished() {
+                self.transition = None;
+            }
+            self.set_orientation(orientation);
+        }
     }
     
     pub fn get_eye_position(&self) -> Vec3 {
-        let x = self.distance * self.elevation.cos() * self.azimuth.sin();
-        let y = self.distance * self.elevation.sin();
-        let z = self.distance * self.elevation.cos() * self.azimuth.cos();
-        Vec3::new(x, y, z) + self.target
+        match self.mode {
+            CameraMode::Orbit => {
+                let x = self.distance * self.elevation.cos() * self.azimuth.sin();
+                let y = self.distance * self.elevation.sin();
+                let z = self.distance * self.elevation.cos() * self.azimuth.cos();
+                Vec3::new(x, y, z) + self.target
+            }
+            CameraMode::Arcball => self.orientation * Vec3::Z * self.distance + self.target,
+        }
+    }
+
+    fn up(&self) -> Vec3 {
+        match self.mode {
+            CameraMode::Orbit => Vec3::Y,
+            CameraMode::Arcball => self.orientation * Vec3::Y,
+        }
     }
     
     pub fn make_vp_matrix(&self, aspect_ratio: f32) -> Mat4 {
-        let projection = Mat4::perspective_rh(self.fov, aspect_ratio, 1.0, 1000.0);
-        let view = Mat4::look_at_rh(self.get_eye_position(), self.target, Vec3::Y);
+        let projection = match self.projection {
+            Projection::Perspective => {
+                Mat4::perspective_rh(self.fov, aspect_ratio, self.near, self.far)
+            }
+            Projection::Orthographic => {
+                let half_height = self.distance * (self.fov * 0.5).tan();
+                let half_width = half_height * aspect_ratio;
+                Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.near,
+                    self.far,
+                )
+            }
+        };
+        let view = Mat4::look_at_rh(self.get_eye_position(), self.target, self.up());
         projection * view
     }
     
+    /// Ray from the eye through pixel `x`, `y` of a `width` by `height` window
+    pub fn ray(&self, x: f32, y: f32, width: f32, height: f32) -> Ray {
+        let inverse = self.make_vp_matrix(width / height).inverse();
+        let ndc_x = 2.0 * x / width - 1.0;
+        let ndc_y = 1.0 - 2.0 * y / height;
+        let near = inverse.project_point3(Vec3::new(ndc_x, ndc_y, 0.0));
+        let far = inverse.project_point3(Vec3::new(ndc_x, ndc_y, 1.0));
+        Ray::new(near, far - near)
+    }
+
+    /// Pixel a world `point` lands on in a `width` by `height` window
+    pub fn project(&self, point: Vec3, width: f32, height: f32) -> Vec2 {
+        let ndc = self.make_vp_matrix(width / height).project_point3(point);
+        Vec2::new((ndc.x + 1.0) * 0.5 * width, (1.0 - ndc.y) * 0.5 * height)
+    }
+
     // Legacy function for compatibility
     pub fn make_vp_matrix_static(aspect_ratio: f32, distance: f32) -> Mat4 {
         let projection = Mat4::perspective_rh(consts::FRAC_PI_4, aspect_ratio, 1.0, 1000.0);
         let view = Mat4::look_at_rh(Vec3::new(1.0, -2.0, 1.0) * distance, Vec3::ZERO, Vec3::Z);
         projection * view
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pan_stays_in_the_view_plane() {
+        for mode in [CameraMode::Orbit, CameraMode::Arcball] {
+            let mut camera = Camera::new();
+            camera.mode = mode;
+            camera.orbit(0.4, 0.6);
+            let forward = (camera.target - camera.get_eye_position()).normalize();
+            let right = camera.orientation * Vec3::X;
+            camera.pan(Vec2::new(0.0, 100.0), 600.0);
+            let moved = camera.target.normalize();
+            assert!(moved.dot(forward).abs() < 1e-5);
+            assert!(moved.dot(right).abs() < 1e-5);
+            assert!(moved.y > 0.0);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use tween::{
+    BackIn, BackInOut, BackOut, BounceIn, BounceInOut, BounceOut, CircIn, CircInOut, CircOut,
+    CubicIn, CubicInOut, CubicOut, ElasticIn, ElasticInOut, ElasticOut, ExpoIn, ExpoInOut, ExpoOut,
+    Linear, QuadIn, QuadInOut, QuadOut, QuintIn, QuintInOut, QuintOut, SineIn, SineInOut, SineOut,
+    Tween,
+};
+
+/// Curves to ease a value from one key to the next
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Easing {
+    BackIn,
+    BackInOut,
+    BackOut,
+    BounceIn,
+    BounceInOut,
+    BounceOut,
+    CircIn,
+    CircInOut,
+    CircOut,
+    CubicIn,
+    CubicInOut,
+    CubicOut,
+    ElasticIn,
+    ElasticInOut,
+    ElasticOut,
+    ExpoIn,
+    ExpoInOut,
+    ExpoOut,
+    QuadIn,
+    QuadInOut,
+    QuadOut,
+    QuintIn,
+    QuintInOut,
+    QuintOut,
+    SineIn,
+    SineInOut,
+    SineOut,
+    Linear,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 28] = [
+        Easing::BackIn,
+        Easing::BackInOut,
+        Easing::BackOut,
+        Easing::BounceIn,
+        Easing::BounceInOut,
+        Easing::BounceOut,
+        Easing::CircIn,
+        Easing::CircInOut,
+        Easing::CircOut,
+        Easing::CubicIn,
+        Easing::CubicInOut,
+        Easing::CubicOut,
+        Easing::ElasticIn,
+        Easing::ElasticInOut,
+        Easing::ElasticOut,
+        Easing::ExpoIn,
+        Easing::ExpoInOut,
+        Easing::ExpoOut,
+        Easing::QuadIn,
+        Easing::QuadInOut,
+        Easing::QuadOut,
+        Easing::QuintIn,
+        Easing::QuintInOut,
+        Easing::QuintOut,
+        Easing::SineIn,
+        Easing::SineInOut,
+        Easing::SineOut,
+        Easing::Linear,
+    ];
+    pub fn name(&self) -> String {
+        format!("{self:?}")
+    }
+    pub fn to_tween(self) -> Box<dyn Tween<f32>> {
+        match self {
+            Easing::BackIn => Box::new(BackIn),
+            Easing::BackInOut => Box::new(BackInOut),
+            Easing::BackOut => Box::new(BackOut),
+            Easing::BounceIn => Box::new(BounceIn),
+            Easing::BounceInOut => Box::new(BounceInOut),
+            Easing::BounceOut => Box::new(BounceOut),
+            Easing::CircIn => Box::new(CircIn),
+            Easing::CircInOut => Box::new(CircInOut),
+            Easing::CircOut => Box::new(CircOut),
+            Easing::CubicIn => Box::new(CubicIn),
+            Easing::CubicInOut => Box::new(CubicInOut),
+            Easing::CubicOut => Box::new(CubicOut),
+            Easing::ElasticIn => Box::new(ElasticIn),
+            Easing::ElasticInOut => Box::new(ElasticInOut),
+            Easing::ElasticOut => Box::new(ElasticOut),
+            Easing::ExpoIn => Box::new(ExpoIn),
+            Easing::ExpoInOut => Box::new(ExpoInOut),
+            Easing::ExpoOut => Box::new(ExpoOut),
+            Easing::QuadIn => Box::new(QuadIn),
+            Easing::QuadInOut => Box::new(QuadInOut),
+            Easing::QuadOut => Box::new(QuadOut),
+            Easing::QuintIn => Box::new(QuintIn),
+            Easing::QuintInOut => Box::new(QuintInOut),
+            Easing::QuintOut => Box::new(QuintOut),
+            Easing::SineIn => Box::new(SineIn),
+            Easing::SineInOut => Box::new(SineInOut),
+            Easing::SineOut => Box::new(SineOut),
+            Easing::Linear => Box::new(Linear),
+        }
+    }
+    /// Eased progress for `t` going from 0 to 1
+    pub fn ease(self, t: f32) -> f32 {
+        self.to_tween().tween(1.0, t.clamp(0.0, 1.0))
+    }
+}
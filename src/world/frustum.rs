@@ -0,0 +1,106 @@
+use crate::geometry::Aabb;
+use glam::{Mat4, Vec4};
+
+/// The six planes bounding what a view-projection matrix can see, each
+/// pointing inwards
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Planes of `vp`, with wgpu's 0 to 1 depth range
+    pub fn from_matrix(vp: &Mat4) -> Self {
+        let [r0, r1, r2, r3] = [vp.row(0), vp.row(1), vp.row(2), vp.row(3)];
+        let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2].map(|plane| {
+            let length = plane.truncate().length();
+            if length > 0.0 { plane / length } else { plane }
+        });
+        Self { planes }
+    }
+    /// False only when `aabb` is entirely behind one of the planes, so a few
+    /// boxes near the corners pass without being seen
+    pub fn intersects(&self, aabb: &Aabb) -> bool {
+        if aabb.is_empty() {
+            return false;
+        }
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the plane normal
+            let normal = plane.truncate();
+            let corner = Vec4::new(
+                if normal.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if normal.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if normal.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+                1.0,
+            );
+            plane.dot(corner) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+    use std::f32::consts::FRAC_PI_2;
+
+    /// Looking down -Y from y = 10, seeing 1 to 100 units ahead with a 90
+    /// degree field of view
+    fn frustum(orthographic: bool) -> Frustum {
+        let projection = if orthographic {
+            Mat4::orthographic_rh(-5.0, 5.0, -5.0, 5.0, 1.0, 100.0)
+        } else {
+            Mat4::perspective_rh(FRAC_PI_2, 1.0, 1.0, 100.0)
+        };
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 10.0, 0.0), Vec3::ZERO, Vec3::Z);
+        Frustum::from_matrix(&(projection * view))
+    }
+
+    fn cube(center: Vec3, half_size: f32) -> Aabb {
+        Aabb {
+            min: center - Vec3::splat(half_size),
+            max: center + Vec3::splat(half_size),
+        }
+    }
+
+    #[test]
+    fn box_inside_is_seen() {
+        for orthographic in [false, true] {
+            let frustum = frustum(orthographic);
+            assert!(frustum.intersects(&cube(Vec3::ZERO, 1.0)));
+            assert!(frustum.intersects(&cube(Vec3::new(2.0, 0.0, -2.0), 0.5)));
+        }
+    }
+
+    #[test]
+    fn box_outside_is_culled() {
+        for orthographic in [false, true] {
+            let frustum = frustum(orthographic);
+            // behind the eye, past the far plane, off to either side, too low
+            assert!(!frustum.intersects(&cube(Vec3::new(0.0, 20.0, 0.0), 1.0)));
+            assert!(!frustum.intersects(&cube(Vec3::new(0.0, -200.0, 0.0), 1.0)));
+            assert!(!frustum.intersects(&cube(Vec3::new(30.0, 0.0, 0.0), 1.0)));
+            assert!(!frustum.intersects(&cube(Vec3::new(-30.0, 0.0, 0.0), 1.0)));
+            assert!(!frustum.intersects(&cube(Vec3::new(0.0, 0.0, -30.0), 1.0)));
+        }
+    }
+
+    #[test]
+    fn box_across_a_plane_is_seen() {
+        for orthographic in [false, true] {
+            let frustum = frustum(orthographic);
+            // half past the right side, across the near plane, across the far plane
+            let side = if orthographic { 5.0 } else { 10.0 };
+            assert!(frustum.intersects(&cube(Vec3::new(side, 0.0, 0.0), 1.0)));
+            assert!(!frustum.intersects(&cube(Vec3::new(side + 3.0, 0.0, 0.0), 1.0)));
+            assert!(frustum.intersects(&cube(Vec3::new(0.0, 9.0, 0.0), 1.0)));
+            assert!(frustum.intersects(&cube(Vec3::new(0.0, -90.0, 0.0), 1.0)));
+            // bigger than the whole view
+            assert!(frustum.intersects(&cube(Vec3::ZERO, 500.0)));
+        }
+    }
+
+    #[test]
+    fn empty_box_is_culled() {
+        assert!(!frustum(false).intersects(&Aabb::EMPTY));
+    }
+}
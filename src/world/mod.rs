@@ -1,14 +1,45 @@
+mod animation;
 mod camera;
+mod easing;
+mod frustum;
 mod light;
 mod node;
+mod ray;
 mod renderer;
+mod scene;
+mod view_layout;
+pub use animation::Animation;
+pub use animation::Animator;
+pub use animation::Clip;
+pub use animation::LoopMode;
+pub use animation::Track;
 pub use camera::Camera;
+pub use camera::CameraMode;
+pub use camera::CameraView;
+pub use camera::Projection;
+pub use easing::Easing;
+pub use frustum::Frustum;
 pub use light::Light;
+pub use node::descendants;
+pub use node::find_by_tag;
+pub use node::find_path;
+pub use node::is_shown;
 pub use node::new_entity;
+pub use node::LAYER_ALL;
 pub use node::new_group;
 pub use node::new_light;
+pub use node::node_path;
+pub use node::world_bounds;
+pub use ray::raycast;
+pub use ray::Ray;
 pub use node::Node;
 pub use node::NodeRef;
+pub use node::Variant;
 pub use renderer::Renderer;
 pub use renderer::MAX_ENTITY;
 pub use renderer::MAX_LIGHT;
+pub use renderer::MAX_VIEWPORT;
+pub use renderer::Viewport;
+pub use renderer::FULL_VIEW;
+pub use scene::SceneNode;
+pub use view_layout::{layout_panel, ViewLayout};
@@ -1,7 +1,11 @@
-use crate::geometry::Mesh;
+use crate::geometry::{Aabb, Mesh};
 use crate::material::Shader;
 use glam::{f32::Quat, EulerRot, Mat4, Vec3};
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    rc::{Rc, Weak},
+};
 use wgpu::Color;
 
 pub enum Variant {
@@ -12,24 +16,53 @@ pub enum Variant {
 
 pub type NodeRef = Rc<RefCell<NodeData>>;
 
+/// Render layer entities are on unless told otherwise
+pub const LAYER_DEFAULT: u32 = 1;
+pub const LAYER_ALL: u32 = u32::MAX;
+
 pub struct NodeData {
+    /// Used to address the node by path from one of its ancestors
+    pub name: Option<String>,
+    pub tags: Vec<String>,
+    /// Free-form numbers for whoever uses the node, kept in scene files
+    pub extras: BTreeMap<String, f32>,
+    /// Hiding a node hides everything below it too
+    pub visible: bool,
+    /// Bit mask of the render layers the node is drawn on
+    pub layers: u32,
     pub translation: Vec3,
     pub rotation: Quat,
     pub scale: Vec3,
     pub variant: Variant,
     pub children: Vec<NodeRef>,
-    pub parent: Option<NodeRef>,
+    /// Weak so a parent and its children don't keep each other alive
+    pub parent: Option<Weak<RefCell<NodeData>>>,
+    /// Cached transform from this node to the world, valid unless `dirty`
+    world: Mat4,
+    /// Set on the whole subtree when a transform above changes
+    dirty: bool,
+    /// Cached `world_bounds`, cleared with `dirty` and on every node above
+    /// whatever changed
+    bounds: Option<Aabb>,
 }
 
 impl Default for NodeData {
     fn default() -> Self {
         Self {
+            name: None,
+            tags: Vec::new(),
+            extras: BTreeMap::new(),
+            visible: true,
+            layers: LAYER_DEFAULT,
             translation: Vec3::ZERO,
             rotation: Quat::IDENTITY,
             scale: Vec3::ONE,
             variant: Variant::Group,
             children: Vec::new(),
             parent: None,
+            world: Mat4::IDENTITY,
+            dirty: true,
+            bounds: None,
         }
     }
 }
@@ -52,8 +85,154 @@ pub fn new_entity(geometry: Rc<Mesh>, shader: Rc<dyn Shader>) -> NodeRef {
     }))
 }
 
+/// Parent of `node`, if it is in a tree and the parent is still alive
+pub fn get_parent(node: &NodeRef) -> Option<NodeRef> {
+    node.borrow().parent.as_ref()?.upgrade()
+}
+
+/// Walks every node below a starting node, parents before their children
+pub struct Descendants {
+    stack: Vec<NodeRef>,
+}
+
+impl Iterator for Descendants {
+    type Item = NodeRef;
+    fn next(&mut self) -> Option<NodeRef> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.borrow().children.iter().rev().cloned());
+        Some(node)
+    }
+}
+
+/// Every node below `node`, not including it
+pub fn descendants(node: &NodeRef) -> Descendants {
+    Descendants {
+        stack: node.borrow().children.iter().rev().cloned().collect(),
+    }
+}
+
+/// Nodes below `node` carrying `tag`
+pub fn find_by_tag<'a>(node: &NodeRef, tag: &'a str) -> impl Iterator<Item = NodeRef> + 'a {
+    descendants(node).filter(move |node| node.borrow().tags.iter().any(|t| t == tag))
+}
+
+/// Follow names separated by `/` down from `node`, like `rubik/pieces/piece 1,1,1`
+pub fn find_path(node: &NodeRef, path: &str) -> Option<NodeRef> {
+    let mut node = node.clone();
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        let child = node
+            .borrow()
+            .children
+            .iter()
+            .find(|child| child.borrow().name.as_deref() == Some(name))
+            .cloned()?;
+        node = child;
+    }
+    Some(node)
+}
+
+/// Names from the top of the tree down to `node`, unnamed nodes showing as `_`
+pub fn node_path(node: &NodeRef) -> String {
+    let mut names = Vec::new();
+    let mut current = Some(node.clone());
+    while let Some(node) = current {
+        if get_parent(&node).is_none() {
+            break;
+        }
+        names.push(node.borrow().name.clone().unwrap_or_else(|| "_".into()));
+        current = get_parent(&node);
+    }
+    names.reverse();
+    names.join("/")
+}
+
+/// Whether a camera drawing `layers` would draw `node`: it and every node
+/// above it visible, and the node itself on one of the layers
+pub fn is_shown(node: &NodeRef, layers: u32) -> bool {
+    if node.borrow().layers & layers == 0 {
+        return false;
+    }
+    let mut current = Some(node.clone());
+    while let Some(node) = current {
+        if !node.borrow().visible {
+            return false;
+        }
+        current = get_parent(&node);
+    }
+    true
+}
+
+/// World space box around every visible entity at or below `node`, groups
+/// taking the union of their children. Kept on the node until something in
+/// its subtree changes.
+pub fn world_bounds(node: &NodeRef) -> Aabb {
+    if !node.borrow().visible {
+        return Aabb::EMPTY;
+    }
+    if let Some(bounds) = node.borrow().bounds {
+        return bounds;
+    }
+    let transform = node.world_transform();
+    let bounds = {
+        let data = node.borrow();
+        let own = match &data.variant {
+            Variant::Entity(mesh, _) => mesh.bounds.transformed(&transform),
+            _ => Aabb::EMPTY,
+        };
+        data.children
+            .iter()
+            .fold(own, |bounds, child| bounds.union(&world_bounds(child)))
+    };
+    node.borrow_mut().bounds = Some(bounds);
+    bounds
+}
+
+/// Forget the bounds of `node` and of every node above it
+fn clear_bounds(node: &NodeRef) {
+    let mut data = node.borrow_mut();
+    data.bounds = None;
+    data.clear_parent_bounds();
+}
+
+/// Flag `node` and everything below it for a world transform update. A dirty
+/// node only has dirty children, so we stop where the flag is already set.
+fn mark_dirty(node: &NodeRef) {
+    let mut node = node.borrow_mut();
+    if !node.dirty {
+        node.mark_dirty();
+    }
+}
+
+impl NodeData {
+    fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.bounds = None;
+        for child in self.children.iter() {
+            mark_dirty(child);
+        }
+    }
+    /// Forget the bounds of every node above, they may have changed with
+    /// this one
+    fn clear_parent_bounds(&self) {
+        let mut parent = self.parent.as_ref().and_then(Weak::upgrade);
+        while let Some(node) = parent {
+            let mut data = node.borrow_mut();
+            data.bounds = None;
+            parent = data.parent.as_ref().and_then(Weak::upgrade);
+        }
+    }
+    /// Transform changed: this subtree moves and so may the bounds above
+    fn moved(&mut self) {
+        self.mark_dirty();
+        self.clear_parent_bounds();
+    }
+}
+
 pub trait Node {
-    fn get_translation(&self) -> Vec3;
+    fn set_name(&mut self, name: &str);
+    fn add_tag(&mut self, tag: &str);
+    fn set_visible(&mut self, visible: bool);
+    fn set_layers(&mut self, layers: u32);
     fn translate(&mut self, x: f32, y: f32, z: f32);
     // fn translate_x(&mut self, x: f32);
     // fn translate_y(&mut self, y: f32);
@@ -65,13 +244,27 @@ pub trait Node {
     fn scale_uniform(&mut self, v: f32) {
         self.scale(v, v, v)
     }
+    /// Draw a different mesh, does nothing unless the node is an entity
+    fn set_mesh(&mut self, mesh: Rc<Mesh>);
     fn rotate_quat(&mut self, q: Quat);
     fn rotate(&mut self, x: f32, y: f32, z: f32);
     fn rotate_x(&mut self, x: f32);
     fn rotate_y(&mut self, y: f32);
     fn rotate_z(&mut self, z: f32);
     fn calculate_transform(&self) -> Mat4;
+    /// Transform from this node to the world, through all its parents
+    fn world_transform(&self) -> Mat4;
     fn add_child(&mut self, node: NodeRef);
+    /// Move `node` under this one without moving it in the world
+    fn add_child_keep_world(&mut self, node: NodeRef) {
+        let local = self.world_transform().inverse() * node.world_transform();
+        let (scale, rotation, translation) = local.to_scale_rotation_translation();
+        let mut child = node.clone();
+        child.translate(translation.x, translation.y, translation.z);
+        child.rotate_quat(rotation);
+        child.scale(scale.x, scale.y, scale.z);
+        self.add_child(node);
+    }
     fn extract_child_if<F>(&mut self, filter: F) -> Vec<NodeRef>
     where
         F: Fn(&NodeRef) -> bool;
@@ -80,11 +273,26 @@ pub trait Node {
     }
 }
 impl Node for NodeData {
-    fn get_translation(&self) -> Vec3 {
-        self.translation
+    fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_string());
+    }
+    fn add_tag(&mut self, tag: &str) {
+        if !self.tags.iter().any(|t| t == tag) {
+            self.tags.push(tag.to_string());
+        }
+    }
+    fn set_visible(&mut self, visible: bool) {
+        if self.visible != visible {
+            self.visible = visible;
+            self.clear_parent_bounds();
+        }
+    }
+    fn set_layers(&mut self, layers: u32) {
+        self.layers = layers;
     }
     fn translate(&mut self, x: f32, y: f32, z: f32) {
         self.translation = Vec3::new(x, y, z);
+        self.moved();
     }
     // fn translate_x(&mut self, x: f32) {
     //     self.translate(x, 0.0, 0.0)
@@ -97,6 +305,7 @@ impl Node for NodeData {
     // }
     fn scale(&mut self, x: f32, y: f32, z: f32) {
         self.scale = Vec3::new(x, y, z);
+        self.moved();
     }
     // fn scale_x(&mut self, x: f32) {
     //     self.scale(x, 0.0, 0.0)
@@ -109,9 +318,10 @@ impl Node for NodeData {
     // }
     fn rotate_quat(&mut self, q: Quat) {
         self.rotation = q;
+        self.moved();
     }
     fn rotate(&mut self, x: f32, y: f32, z: f32) {
-        self.rotation = Quat::from_euler(EulerRot::XYZ, x, y, z);
+        self.rotate_quat(Quat::from_euler(EulerRot::XYZ, x, y, z));
     }
     fn rotate_x(&mut self, x: f32) {
         self.rotate(x, 0.0, 0.0);
@@ -125,8 +335,26 @@ impl Node for NodeData {
     fn calculate_transform(&self) -> Mat4 {
         Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
     }
+    fn world_transform(&self) -> Mat4 {
+        if !self.dirty {
+            return self.world;
+        }
+        let parent = self.parent.as_ref().and_then(Weak::upgrade);
+        let parent = parent.map_or(Mat4::IDENTITY, |parent| parent.world_transform());
+        parent * self.calculate_transform()
+    }
+    fn set_mesh(&mut self, mesh: Rc<Mesh>) {
+        if let Variant::Entity(old, _) = &mut self.variant {
+            *old = mesh;
+            self.bounds = None;
+            self.clear_parent_bounds();
+        }
+    }
     fn add_child(&mut self, child: NodeRef) {
+        mark_dirty(&child);
         self.children.push(child);
+        self.bounds = None;
+        self.clear_parent_bounds();
     }
     fn extract_child_if<F>(&mut self, filter: F) -> Vec<NodeRef>
     where
@@ -136,22 +364,48 @@ impl Node for NodeData {
         let mut i = 0;
         while i < self.children.len() {
             if filter(&self.children[i]) {
-                ret.push(self.children.swap_remove(i));
+                let child = self.children.swap_remove(i);
+                child.borrow_mut().parent = None;
+                mark_dirty(&child);
+                ret.push(child);
             } else {
                 i += 1;
             }
         }
+        if !ret.is_empty() {
+            self.bounds = None;
+            self.clear_parent_bounds();
+        }
         ret
     }
     fn extract_all_child(&mut self) -> Vec<NodeRef> {
-        let ret = self.children.clone();
-        self.children = Vec::new();
+        let ret = std::mem::take(&mut self.children);
+        for child in ret.iter() {
+            child.borrow_mut().parent = None;
+            mark_dirty(child);
+        }
+        if !ret.is_empty() {
+            self.bounds = None;
+            self.clear_parent_bounds();
+        }
         ret
     }
 }
 impl Node for NodeRef {
-    fn get_translation(&self) -> Vec3 {
-        self.borrow().translation
+    fn set_name(&mut self, name: &str) {
+        self.borrow_mut().set_name(name);
+    }
+    fn add_tag(&mut self, tag: &str) {
+        self.borrow_mut().add_tag(tag);
+    }
+    fn set_visible(&mut self, visible: bool) {
+        self.borrow_mut().set_visible(visible);
+    }
+    fn set_mesh(&mut self, mesh: Rc<Mesh>) {
+        self.borrow_mut().set_mesh(mesh);
+    }
+    fn set_layers(&mut self, layers: u32) {
+        self.borrow_mut().set_layers(layers);
     }
     fn translate(&mut self, x: f32, y: f32, z: f32) {
         self.borrow_mut().translate(x, y, z)
@@ -196,9 +450,25 @@ impl Node for NodeRef {
         let node = self.borrow();
         Mat4::from_scale_rotation_translation(node.scale, node.rotation, node.translation)
     }
+    fn world_transform(&self) -> Mat4 {
+        if !self.borrow().dirty {
+            return self.borrow().world;
+        }
+        let parent = get_parent(self).map_or(Mat4::IDENTITY, |parent| parent.world_transform());
+        let mut node = self.borrow_mut();
+        node.world = parent * node.calculate_transform();
+        node.dirty = false;
+        node.world
+    }
     fn add_child(&mut self, child: NodeRef) {
+        // a node lives in one place in the tree, leave the old parent first
+        if let Some(mut parent) = get_parent(&child) {
+            parent.extract_child_if(|node| Rc::ptr_eq(node, &child));
+        }
         self.borrow_mut().children.push(child.clone());
-        child.borrow_mut().parent = Some(self.clone());
+        child.borrow_mut().parent = Some(Rc::downgrade(self));
+        mark_dirty(&child);
+        clear_bounds(self);
     }
     fn extract_child_if<F>(&mut self, filter: F) -> Vec<NodeRef>
     where
@@ -210,3 +480,159 @@ impl Node for NodeRef {
         self.borrow_mut().extract_all_child()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_dropped_tree_is_freed() {
+        let mut root = new_group();
+        let mut child = new_group();
+        let grandchild = new_group();
+        child.add_child(grandchild.clone());
+        root.add_child(child.clone());
+        let handles = [&root, &child, &grandchild].map(Rc::downgrade);
+        drop((root, child, grandchild));
+        assert!(handles.iter().all(|handle| handle.upgrade().is_none()));
+    }
+
+    #[test]
+    fn a_child_outlives_its_parent() {
+        let mut root = new_group();
+        let child = new_group();
+        root.add_child(child.clone());
+        assert!(get_parent(&child).is_some_and(|parent| Rc::ptr_eq(&parent, &root)));
+        let handle = Rc::downgrade(&root);
+        drop(root);
+        assert!(handle.upgrade().is_none());
+        assert!(get_parent(&child).is_none());
+    }
+
+    #[test]
+    fn add_child_takes_the_child_from_its_old_parent() {
+        let mut a = new_group();
+        let mut b = new_group();
+        let child = new_group();
+        a.add_child(child.clone());
+        b.add_child(child.clone());
+        assert!(a.borrow().children.is_empty());
+        assert_eq!(b.borrow().children.len(), 1);
+        assert!(get_parent(&child).is_some_and(|parent| Rc::ptr_eq(&parent, &b)));
+        // only b holds it now
+        assert_eq!(Rc::strong_count(&child), 2);
+    }
+
+    #[test]
+    fn world_transform_follows_a_moved_grandparent() {
+        let mut grandparent = new_group();
+        let mut parent = new_group();
+        let mut child = new_group();
+        parent.add_child(child.clone());
+        grandparent.add_child(parent.clone());
+        parent.translate(0.0, 1.0, 0.0);
+        child.translate(1.0, 0.0, 0.0);
+        let origin = |node: &NodeRef| node.world_transform().transform_point3(Vec3::ZERO);
+        assert!(origin(&child).abs_diff_eq(Vec3::new(1.0, 1.0, 0.0), 1e-6));
+        // the cached transforms below have to notice
+        grandparent.translate(0.0, 0.0, 2.0);
+        grandparent.rotate_z(std::f32::consts::FRAC_PI_2);
+        assert!(origin(&child).abs_diff_eq(Vec3::new(-1.0, 1.0, 2.0), 1e-6));
+        assert!(origin(&parent).abs_diff_eq(Vec3::new(-1.0, 0.0, 2.0), 1e-6));
+        grandparent.scale_uniform(2.0);
+        assert!(origin(&child).abs_diff_eq(Vec3::new(-2.0, 2.0, 2.0), 1e-6));
+    }
+
+    #[test]
+    fn add_child_keep_world_leaves_the_node_in_place() {
+        let mut turned = new_group();
+        turned.translate(1.0, -2.0, 0.5);
+        turned.rotate(0.3, -0.7, 1.1);
+        turned.scale_uniform(1.5);
+        let mut held = new_group();
+        held.translate(0.5, 0.0, 3.0);
+        held.rotate(-0.4, 0.2, 0.0);
+        let mut piece = new_group();
+        piece.translate(2.0, 1.0, -1.0);
+        piece.rotate_y(0.8);
+        held.add_child(piece.clone());
+        let before = piece.world_transform();
+        turned.add_child_keep_world(piece.clone());
+        assert!(held.borrow().children.is_empty());
+        assert!(piece.world_transform().abs_diff_eq(before, 1e-5));
+    }
+
+    /// rubik > pieces > (corner, edge), rubik > unnamed > light
+    fn tagged_tree() -> NodeRef {
+        let node = |name: Option<&str>, tag: &str| {
+            let mut node = new_group();
+            if let Some(name) = name {
+                node.set_name(name);
+            }
+            node.add_tag(tag);
+            node
+        };
+        let mut root = node(Some("rubik"), "cube");
+        let mut pieces = node(Some("pieces"), "group");
+        pieces.add_child(node(Some("corner"), "piece"));
+        pieces.add_child(node(Some("edge"), "piece"));
+        let mut unnamed = node(None, "group");
+        unnamed.add_child(node(Some("light"), "light"));
+        root.add_child(pieces);
+        root.add_child(unnamed);
+        let mut top = new_group();
+        top.add_child(root);
+        top
+    }
+
+    fn names(nodes: impl Iterator<Item = NodeRef>) -> Vec<String> {
+        nodes.map(|node| node.borrow().name.clone().unwrap_or_default()).collect()
+    }
+
+    #[test]
+    fn descendants_come_parents_first() {
+        let top = tagged_tree();
+        assert_eq!(names(descendants(&top)), ["rubik", "pieces", "corner", "edge", "", "light"]);
+        let leaf = find_path(&top, "rubik/pieces/edge").unwrap();
+        assert_eq!(descendants(&leaf).count(), 0);
+    }
+
+    #[test]
+    fn nodes_are_found_by_tag_and_path() {
+        let top = tagged_tree();
+        assert_eq!(names(find_by_tag(&top, "piece")), ["corner", "edge"]);
+        assert_eq!(find_by_tag(&top, "group").count(), 2);
+        assert_eq!(find_by_tag(&top, "nothing").count(), 0);
+        let corner = find_path(&top, "rubik/pieces/corner").unwrap();
+        assert_eq!(node_path(&corner), "rubik/pieces/corner");
+        assert!(Rc::ptr_eq(&find_path(&top, "/rubik//pieces/corner").unwrap(), &corner));
+        assert!(Rc::ptr_eq(&find_path(&top, "").unwrap(), &top));
+        assert!(find_path(&top, "rubik/corner").is_none());
+        let light = find_by_tag(&top, "light").next().unwrap();
+        assert_eq!(node_path(&light), "rubik/_/light");
+    }
+
+    #[test]
+    fn hidden_parents_and_other_layers_hide_a_node() {
+        let mut top = new_group();
+        let mut parent = new_group();
+        let mut child = new_group();
+        child.set_layers(1 << 3);
+        parent.add_child(child.clone());
+        top.add_child(parent.clone());
+        assert!(is_shown(&child, LAYER_ALL));
+        assert!(is_shown(&child, 1 << 3 | 1 << 5));
+        assert!(!is_shown(&child, LAYER_DEFAULT));
+        assert!(is_shown(&parent, LAYER_DEFAULT));
+        top.set_visible(false);
+        assert!(!is_shown(&child, LAYER_ALL));
+        assert!(!is_shown(&parent, LAYER_ALL));
+        top.set_visible(true);
+        parent.set_visible(false);
+        assert!(is_shown(&top, LAYER_ALL));
+        assert!(!is_shown(&child, LAYER_ALL));
+        // moving it out from under the hidden parent shows it again
+        top.add_child(child.clone());
+        assert!(is_shown(&child, LAYER_ALL));
+    }
+}
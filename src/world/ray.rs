@@ -0,0 +1,260 @@
+use crate::geometry::Aabb;
+use crate::world::node::Variant;
+use crate::world::{Node, NodeRef};
+use glam::{Mat3, Vec3};
+
+/// A half line from `origin` along `direction`
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: Vec3,
+    /// Normalized
+    pub direction: Vec3,
+}
+
+/// Where a ray first meets an entity
+#[derive(Clone)]
+pub struct RayHit {
+    pub node: NodeRef,
+    /// Distance along the ray in world units
+    pub distance: f32,
+    pub position: Vec3,
+    /// World space normal of the triangle hit, facing the way it is wound
+    pub normal: Vec3,
+    /// Index of the triangle in the mesh, its vertices are `indices[3 * i..3 * i + 3]`
+    pub triangle: usize,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+    /// Distance to where the ray enters `aabb`, 0 if it starts inside
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        if aabb.is_empty() {
+            return None;
+        }
+        let inverse = self.direction.recip();
+        let t1 = (aabb.min - self.origin) * inverse;
+        let t2 = (aabb.max - self.origin) * inverse;
+        let near = t1.min(t2).max_element().max(0.0);
+        let far = t1.max(t2).min_element();
+        (near <= far).then_some(near)
+    }
+    /// Distance to triangle `a b c` from either side (Möller–Trumbore)
+    pub fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+        let ab = b - a;
+        let ac = c - a;
+        let p = self.direction.cross(ac);
+        let det = ab.dot(p);
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        let inverse = 1.0 / det;
+        let s = self.origin - a;
+        let u = s.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(ab);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = ac.dot(q) * inverse;
+        (t >= 0.0).then_some(t)
+    }
+}
+
+/// Nearest visible entity on `layers` below `root` hit by `ray`
+pub fn raycast(root: &NodeRef, ray: &Ray, layers: u32) -> Option<RayHit> {
+    let mut nearest: Option<RayHit> = None;
+    let mut q = vec![root.clone()];
+    while let Some(node) = q.pop() {
+        if !node.borrow().visible {
+            continue;
+        }
+        let hit = raycast_entity(&node, ray, layers);
+        if let Some(hit) = hit
+            && nearest.as_ref().is_none_or(|nearest| hit.distance < nearest.distance)
+        {
+            nearest = Some(hit);
+        }
+        q.extend(node.borrow().children.iter().cloned());
+    }
+    nearest
+}
+
+/// Test the mesh of `node` alone, in its own space so the mesh bounds can
+/// rule most entities out before looking at triangles
+fn raycast_entity(node: &NodeRef, ray: &Ray, layers: u32) -> Option<RayHit> {
+    let mesh = {
+        let data = node.borrow();
+        let Variant::Entity(mesh, _) = &data.variant else {
+            return None;
+        };
+        if data.layers & layers == 0 {
+            return None;
+        }
+        mesh.clone()
+    };
+    // refreshing a dirty transform borrows the node mutably
+    let world = node.world_transform();
+    let inverse = world.inverse();
+    let local = Ray::new(
+        inverse.transform_point3(ray.origin),
+        inverse.transform_vector3(ray.direction),
+    );
+    local.intersect_aabb(&mesh.bounds)?;
+    let vertex = |i: u32| {
+        let [x, y, z, _] = mesh.vertices[i as usize].position;
+        Vec3::new(x, y, z)
+    };
+    let (triangle, distance, [a, b, c]) = mesh
+        .indices
+        .chunks_exact(3)
+        .enumerate()
+        .filter_map(|(i, t)| {
+            let corners = [vertex(t[0]), vertex(t[1]), vertex(t[2])];
+            let distance = local.intersect_triangle(corners[0], corners[1], corners[2])?;
+            Some((i, distance, corners))
+        })
+        .min_by(|(_, a, _), (_, b, _)| a.total_cmp(b))?;
+    let position = world.transform_point3(local.at(distance));
+    let normal_matrix = Mat3::from_mat4(inverse).transpose();
+    Some(RayHit {
+        node: node.clone(),
+        distance: position.distance(ray.origin),
+        position,
+        normal: (normal_matrix * (b - a).cross(c - a)).normalize(),
+        triangle,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Mesh;
+    use crate::material::{Shader, ShaderKind};
+    use crate::world::{new_entity, new_group, Light, LAYER_ALL};
+    use std::rc::Rc;
+    use wgpu::{Queue, RenderPass};
+
+    fn unit_box() -> Aabb {
+        Aabb::from_points([Vec3::splat(-1.0), Vec3::splat(1.0)])
+    }
+
+    #[test]
+    fn ray_hits_box_in_front() {
+        let ray = Ray::new(Vec3::new(0.0, -5.0, 0.0), Vec3::Y);
+        assert_eq!(ray.intersect_aabb(&unit_box()), Some(4.0));
+        let slanted = Ray::new(Vec3::new(-5.0, -5.0, 0.5), Vec3::new(1.0, 1.0, 0.0));
+        let distance = slanted.intersect_aabb(&unit_box()).unwrap();
+        assert!(slanted.at(distance).abs_diff_eq(Vec3::new(-1.0, -1.0, 0.5), 1e-5));
+    }
+
+    #[test]
+    fn ray_misses_box() {
+        // passing beside it, pointing away, and starting past it
+        let beside = Ray::new(Vec3::new(2.0, -5.0, 0.0), Vec3::Y);
+        assert_eq!(beside.intersect_aabb(&unit_box()), None);
+        let away = Ray::new(Vec3::new(0.0, -5.0, 0.0), Vec3::NEG_Y);
+        assert_eq!(away.intersect_aabb(&unit_box()), None);
+        let past = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::Y);
+        assert_eq!(past.intersect_aabb(&unit_box()), None);
+        let grazing = Ray::new(Vec3::new(-5.0, -5.0, 0.0), Vec3::new(1.0, 0.2, 0.0));
+        assert_eq!(grazing.intersect_aabb(&unit_box()), None);
+        assert_eq!(Ray::new(Vec3::ZERO, Vec3::X).intersect_aabb(&Aabb::EMPTY), None);
+    }
+
+    #[test]
+    fn ray_from_inside_box_hits_at_once() {
+        for direction in [Vec3::X, Vec3::NEG_Z, Vec3::new(1.0, -2.0, 0.5)] {
+            let ray = Ray::new(Vec3::new(0.2, 0.3, -0.4), direction);
+            assert_eq!(ray.intersect_aabb(&unit_box()), Some(0.0));
+        }
+    }
+
+    const TRIANGLE: [Vec3; 3] = [
+        Vec3::new(-1.0, 0.0, -1.0),
+        Vec3::new(1.0, 0.0, -1.0),
+        Vec3::new(0.0, 0.0, 1.0),
+    ];
+
+    #[test]
+    fn ray_hits_triangle_from_either_side() {
+        let [a, b, c] = TRIANGLE;
+        let front = Ray::new(Vec3::new(0.0, -3.0, 0.0), Vec3::Y);
+        assert!((front.intersect_triangle(a, b, c).unwrap() - 3.0).abs() < 1e-5);
+        let back = Ray::new(Vec3::new(0.2, 2.0, -0.5), Vec3::NEG_Y);
+        assert!((back.intersect_triangle(a, b, c).unwrap() - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn ray_misses_triangle() {
+        let [a, b, c] = TRIANGLE;
+        // outside its corner, behind the ray, and along its plane
+        let outside = Ray::new(Vec3::new(0.9, -3.0, 0.9), Vec3::Y);
+        assert_eq!(outside.intersect_triangle(a, b, c), None);
+        let behind = Ray::new(Vec3::new(0.0, 3.0, 0.0), Vec3::Y);
+        assert_eq!(behind.intersect_triangle(a, b, c), None);
+        let parallel = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X);
+        assert_eq!(parallel.intersect_triangle(a, b, c), None);
+    }
+
+    /// Draws nothing, the ray casts only look at meshes
+    struct NoShader;
+
+    impl Shader for NoShader {
+        fn kind(&self) -> ShaderKind {
+            ShaderKind::Unlit
+        }
+        fn set_pipeline<'a>(&'a self, _: &mut RenderPass<'a>, _: u64, _: u64) {}
+        fn write_transform_data(&self, _: &Queue, _: u64, _: &[f32; 16]) {}
+        fn write_rotation_data(&self, _: &Queue, _: u64, _: &[f32; 16]) {}
+        fn write_time_data(&self, _: &Queue, _: f32) {}
+        fn write_camera_data(&self, _: &Queue, _: u64, _: &[f32; 16]) {}
+        fn write_light_data(&self, _: &Queue, _: &[Light]) {}
+    }
+
+    /// Meshes need a device for their buffers, `None` on a machine without
+    /// any adapter at all
+    fn device() -> Option<wgpu::Device> {
+        pollster::block_on(async {
+            let adapter = wgpu::Instance::default()
+                .request_adapter(&wgpu::RequestAdapterOptions::default())
+                .await
+                .ok()?;
+            let (device, _) = adapter.request_device(&Default::default()).await.ok()?;
+            Some(device)
+        })
+    }
+
+    #[test]
+    fn raycast_follows_a_moved_parent() {
+        let Some(device) = device() else {
+            eprintln!("no graphics adapter, skipping");
+            return;
+        };
+        let mesh = Rc::new(Mesh::new_cube(0xffffffff, &device));
+        let mut root = new_group();
+        let mut parent = new_group();
+        let cube = new_entity(mesh, Rc::new(NoShader));
+        parent.add_child(cube.clone());
+        root.add_child(parent.clone());
+        let down = Ray::new(Vec3::new(5.0, 0.0, 10.0), Vec3::NEG_Z);
+        assert!(raycast(&root, &down, LAYER_ALL).is_none());
+        // the cube's world transform is dirty until something asks for it
+        parent.translate(5.0, 0.0, 0.0);
+        let hit = raycast(&root, &down, LAYER_ALL).unwrap();
+        assert!(Rc::ptr_eq(&hit.node, &cube));
+        assert!((hit.distance - 9.0).abs() < 1e-4);
+        assert!(hit.normal.abs_diff_eq(Vec3::Z, 1e-5));
+        assert!(raycast(&root, &down, 0).is_none());
+    }
+}
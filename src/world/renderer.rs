@@ -1,8 +1,11 @@
-use crate::world::{node, Camera, Light, Node, NodeRef};
+use crate::geometry::{Aabb, Mesh};
+use crate::material::Shader;
+use crate::world::{node, world_bounds, Camera, Frustum, Light, Node, NodeRef, Ray};
 use egui_wgpu::{RendererOptions, ScreenDescriptor};
-use glam::{Mat4, Vec4};
+use glam::{Mat4, Vec2, Vec3, Vec4};
 use std::cmp::max;
 use std::mem::size_of;
+use std::rc::Rc;
 use std::sync::Arc;
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::Instant;
@@ -16,6 +19,9 @@ use winit::window::Window;
 
 pub const MAX_ENTITY: u64 = 100000;
 pub const MAX_LIGHT: u64 = 10;
+pub const MAX_VIEWPORT: u64 = 8;
+/// The whole window, as a viewport rect
+pub const FULL_VIEW: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
 const CLEAR_COLOR: Color = Color {
     r: 0.01233333333,
     g: 0.01233333333,
@@ -23,8 +29,114 @@ const CLEAR_COLOR: Color = Color {
     a: 1.0,
 };
 
+/// Counts from the last frame drawn
+#[derive(Clone, Copy, Default)]
+pub struct RenderStats {
+    pub drawn: usize,
+    /// Entities skipped for being outside the camera's view
+    pub culled: usize,
+}
+
+/// A camera drawn into part of the window
+pub struct Viewport {
+    pub camera: Camera,
+    /// Left, top, width and height, as fractions of the window
+    pub rect: [f32; 4],
+    /// Draw this instead of the renderer's own scene
+    pub scene: Option<NodeRef>,
+}
+
+/// Pixel rect `x`, `y`, `width`, `height`
+type PixelRect = [u32; 4];
+
+/// An entity to draw: its mesh, shader, world transform, world rotation and
+/// render layers
+type DrawNode = (Rc<Mesh>, Rc<dyn Shader>, Mat4, Mat4, u32);
+
+/// A visible node in the order the scene was walked, so a camera can skip
+/// whole subtrees it doesn't see
+struct Walked {
+    bounds: Aabb,
+    /// Index in the walk just past this node's subtree
+    end: usize,
+    /// Entities in the subtree, this node included
+    entities: usize,
+    /// Where the node is in the entities to draw, if it is one
+    entity: Option<usize>,
+}
+
+/// Gather the entities and lights of the visible nodes at or below `node`,
+/// parents before their children
+fn walk(
+    node: &NodeRef,
+    nodes: &mut Vec<DrawNode>,
+    lights: &mut Vec<(Color, f32, Mat4)>,
+    walked: &mut Vec<Walked>,
+) {
+    // hidden nodes take their whole subtree with them
+    if !node.borrow().visible {
+        return;
+    }
+    let transform_mx = node.world_transform();
+    let index = walked.len();
+    walked.push(Walked {
+        bounds: world_bounds(node),
+        end: index,
+        entities: 0,
+        entity: None,
+    });
+    let start = nodes.len();
+    let data = node.borrow();
+    match &data.variant {
+        node::Variant::Entity(geometry, shader) => {
+            let (_scale, rotation, _translation) = transform_mx.to_scale_rotation_translation();
+            let rotation = Mat4::from_quat(rotation);
+            nodes.push((geometry.clone(), shader.clone(), transform_mx, rotation, data.layers));
+            walked[index].entity = Some(start);
+        }
+        node::Variant::Light(color, radius) => {
+            lights.push((*color, *radius, transform_mx));
+        }
+        _ => {}
+    }
+    for child in data.children.iter() {
+        walk(child, nodes, lights, walked);
+    }
+    walked[index].end = walked.len();
+    walked[index].entities = nodes.len() - start;
+}
+
+/// What is left of `rect` once `hole` is cut out of it, as up to 4 rects
+fn subtract(rect: PixelRect, hole: PixelRect) -> Vec<PixelRect> {
+    let [x, y, w, h] = rect;
+    let left = x.max(hole[0]);
+    let top = y.max(hole[1]);
+    let right = (x + w).min(hole[0] + hole[2]);
+    let bottom = (y + h).min(hole[1] + hole[3]);
+    if left >= right || top >= bottom {
+        return vec![rect];
+    }
+    [
+        [x, y, w, top - y],
+        [x, bottom, w, y + h - bottom],
+        [x, top, left - x, bottom - top],
+        [right, top, x + w - right, bottom - top],
+    ]
+    .into_iter()
+    .filter(|[_, _, w, h]| *w > 0 && *h > 0)
+    .collect()
+}
+
 pub struct Renderer {
+    /// The main camera, the one input goes to
     pub camera: Camera,
+    /// Where the main camera is drawn
+    pub viewport: [f32; 4],
+    /// More cameras, drawn over the main one in order
+    pub viewports: Vec<Viewport>,
+    /// Skip entities whose bounds are outside the view
+    pub culling: bool,
+    pub stats: RenderStats,
     pub root: NodeRef,
     pub time: f32,
     pub config: SurfaceConfiguration,
@@ -110,6 +222,10 @@ impl Renderer {
         let egui_renderer = egui_wgpu::Renderer::new(&device, config.format, RendererOptions::default());
         Self {
             camera: Camera::new(),
+            viewport: FULL_VIEW,
+            viewports: Vec::new(),
+            culling: true,
+            stats: RenderStats::default(),
             root: node::new_group(),
             config,
             surface,
@@ -142,6 +258,52 @@ impl Renderer {
         self.depth_texture_view = depth_texture.create_view(&TextureViewDescriptor::default());
     }
 
+    /// `rect` in pixels, clamped to the window
+    fn pixel_rect(&self, rect: [f32; 4]) -> PixelRect {
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+        let x = (rect[0] * width).round().clamp(0.0, width);
+        let y = (rect[1] * height).round().clamp(0.0, height);
+        let w = (rect[2] * width).round().clamp(0.0, width - x);
+        let h = (rect[3] * height).round().clamp(0.0, height - y);
+        [x as u32, y as u32, w as u32, h as u32]
+    }
+
+    /// Left, top, width and height of the main view in pixels
+    pub fn view_rect(&self) -> [f32; 4] {
+        self.pixel_rect(self.viewport).map(|v| v as f32)
+    }
+
+    /// Aspect ratio of the main view
+    pub fn aspect_ratio(&self) -> f32 {
+        let [_, _, width, height] = self.view_rect();
+        width.max(1.0) / height.max(1.0)
+    }
+
+    /// Ray from the main camera through window pixel `x`, `y`
+    pub fn ray(&self, x: f32, y: f32) -> Ray {
+        let [left, top, width, height] = self.view_rect();
+        self.camera
+            .ray(x - left, y - top, width.max(1.0), height.max(1.0))
+    }
+
+    /// Ray from the camera of `viewports[index]` through window pixel `x`,
+    /// `y`, if the pixel is inside that viewport
+    pub fn viewport_ray(&self, index: usize, x: f32, y: f32) -> Option<Ray> {
+        let viewport = self.viewports.get(index)?;
+        let [left, top, width, height] = self.pixel_rect(viewport.rect).map(|v| v as f32);
+        let (x, y) = (x - left, y - top);
+        if x < 0.0 || y < 0.0 || x >= width || y >= height {
+            return None;
+        }
+        Some(viewport.camera.ray(x, y, width, height))
+    }
+
+    /// Window pixel a world `point` lands on in the main view
+    pub fn project(&self, point: Vec3) -> Vec2 {
+        let [left, top, width, height] = self.view_rect();
+        self.camera.project(point, width.max(1.0), height.max(1.0)) + Vec2::new(left, top)
+    }
+
     pub fn draw(&mut self, egui_ctx: &egui::Context, egui_primitives: Vec<egui::ClippedPrimitive>, textures_delta: egui::TexturesDelta) {
         let frame = match self.surface.get_current_texture() {
             Ok(frame) => frame,
@@ -163,30 +325,75 @@ impl Renderer {
         let mut nodes = Vec::new();
         let mut lights: Vec<(Color, f32, Mat4)> = Vec::new();
 
-        // Collect nodes and lights
-        let mut q = Vec::new();
-        q.push((self.root.clone(), Mat4::IDENTITY));
-        let vp_matrix = self.camera.make_vp_matrix(
-            self.config.width as f32 / self.config.height as f32,
-        );
-        while let Some((node, transform_mx)) = q.pop() {
-            match &node.borrow().variant {
-                node::Variant::Entity(geometry, shader) => {
-                    let (_scale, rotation, _translation) =
-                        transform_mx.to_scale_rotation_translation();
-                    let rotation = Mat4::from_quat(rotation);
-                    nodes.push((geometry.clone(), shader.clone(), transform_mx, rotation));
+        // Collect nodes and lights of every scene drawn, the main one first
+        let mut scenes = vec![self.root.clone()];
+        for scene in self.viewports.iter().filter_map(|viewport| viewport.scene.as_ref()) {
+            if !scenes.iter().any(|s| Rc::ptr_eq(s, scene)) {
+                scenes.push(scene.clone());
+            }
+        }
+        let mut walked = Vec::new();
+        let mut ranges = Vec::new();
+        for scene in scenes.iter() {
+            let start = walked.len();
+            walk(scene, &mut nodes, &mut lights, &mut walked);
+            ranges.push((start..walked.len(), world_bounds(scene)));
+        }
+
+        // Pick what each camera sees
+        let mut views = Vec::new();
+        let rects = std::iter::once(self.viewport)
+            .chain(self.viewports.iter().map(|viewport| viewport.rect))
+            .take(MAX_VIEWPORT as usize)
+            .map(|rect| self.pixel_rect(rect))
+            .collect::<Vec<_>>();
+        let view_scenes = std::iter::once(0)
+            .chain(self.viewports.iter().map(|viewport| match &viewport.scene {
+                Some(scene) => scenes.iter().position(|s| Rc::ptr_eq(s, scene)).unwrap_or(0),
+                None => 0,
+            }))
+            .collect::<Vec<_>>();
+        let cameras = std::iter::once(&mut self.camera)
+            .chain(self.viewports.iter_mut().map(|viewport| &mut viewport.camera));
+        let mut drawn = 0;
+        let mut culled = 0;
+        for (k, (camera, rect)) in cameras.zip(rects.iter()).enumerate() {
+            let [_, _, width, height] = *rect;
+            if width == 0 || height == 0 {
+                continue;
+            }
+            let (range, bounds) = &ranges[view_scenes[k]];
+            if !bounds.is_empty() {
+                camera.fit_clip_planes(bounds.center(), bounds.size().length() * 0.5);
+            }
+            let vp_matrix = camera.make_vp_matrix(width as f32 / height as f32);
+            let frustum = Frustum::from_matrix(&vp_matrix);
+            let mut visible = Vec::new();
+            let mut i = range.start;
+            while i < range.end {
+                let entry = &walked[i];
+                if self.culling && !frustum.intersects(&entry.bounds) {
+                    // nothing below can be seen either
+                    culled += entry.entities;
+                    i = entry.end;
+                    continue;
                 }
-                node::Variant::Light(color, radius) => {
-                    lights.push((*color, *radius, transform_mx));
+                if let Some(n) = entry.entity
+                    && nodes[n].4 & camera.layers != 0
+                {
+                    visible.push(n);
                 }
-                _ => {}
+                i += 1;
             }
-            for child in node.borrow().children.iter() {
-                let transform_mx = transform_mx * child.calculate_transform();
-                q.push((child.clone(), transform_mx));
+            drawn += visible.len();
+            // only draw where no later viewport will
+            let mut scissors = vec![*rect];
+            for hole in rects.iter().skip(k + 1) {
+                scissors = scissors.into_iter().flat_map(|piece| subtract(piece, *hole)).collect();
             }
+            views.push((*rect, scissors, vp_matrix, visible));
         }
+        self.stats = RenderStats { drawn, culled };
         let lights = lights
             .into_iter()
             .map(|(color, radius, transform)| {
@@ -209,11 +416,12 @@ impl Renderer {
                 self.device.limits().min_uniform_buffer_offset_alignment as BufferAddress;
             align_to(node_uniform_size, alignment)
         };
+        let camera_uniform_aligned = node_uniform_aligned;
 
         // Update egui textures
         for (id, image_delta) in &textures_delta.set {
             self.egui_renderer
-                .update_texture(&self.device, &self.queue, *id, &image_delta);
+                .update_texture(&self.device, &self.queue, *id, image_delta);
         }
 
         // Create command encoder and render
@@ -246,18 +454,38 @@ impl Renderer {
                 occlusion_query_set: None,
             });
 
-            for (i, (geometry, shader, transform, rotation)) in nodes.iter().enumerate() {
+            for (i, (_, shader, transform, rotation, _)) in nodes.iter().enumerate() {
                 let offset = (node_uniform_aligned * i as u64) as BufferAddress;
-                shader.set_pipeline(&mut rpass, offset);
-                shader.write_camera_data(&self.queue, vp_matrix.as_ref());
                 shader.write_light_data(&self.queue, &lights);
                 shader.write_time_data(&self.queue, self.time);
                 shader.write_transform_data(&self.queue, offset, transform.as_ref());
                 shader.write_rotation_data(&self.queue, offset, rotation.as_ref());
-                rpass.set_index_buffer(geometry.index_buffer.slice(..), IndexFormat::Uint32);
-                rpass.set_vertex_buffer(0, geometry.vertex_buffer.slice(..));
-                let n = geometry.indices.len() as u32;
-                rpass.draw_indexed(0..n, 0, 0..1);
+            }
+            for (v, (rect, scissors, vp_matrix, visible)) in views.iter().enumerate() {
+                let camera_offset = (camera_uniform_aligned * v as u64) as BufferAddress;
+                let [x, y, width, height] = rect.map(|v| v as f32);
+                rpass.set_viewport(x, y, width, height, 0.0, 1.0);
+                // each shader keeps its own camera buffer, written once per view
+                let mut shaders: Vec<&Rc<dyn Shader>> = Vec::new();
+                for &i in visible.iter() {
+                    let shader = &nodes[i].1;
+                    if !shaders.iter().any(|s| Rc::ptr_eq(s, shader)) {
+                        shader.write_camera_data(&self.queue, camera_offset, vp_matrix.as_ref());
+                        shaders.push(shader);
+                    }
+                }
+                for &[x, y, width, height] in scissors.iter() {
+                    rpass.set_scissor_rect(x, y, width, height);
+                    for &i in visible.iter() {
+                        let (geometry, shader, ..) = &nodes[i];
+                        let offset = (node_uniform_aligned * i as u64) as BufferAddress;
+                        shader.set_pipeline(&mut rpass, offset, camera_offset);
+                        rpass.set_index_buffer(geometry.index_buffer.slice(..), IndexFormat::Uint32);
+                        rpass.set_vertex_buffer(0, geometry.vertex_buffer.slice(..));
+                        let n = geometry.indices.len() as u32;
+                        rpass.draw_indexed(0..n, 0, 0..1);
+                    }
+                }
             }
         }
 
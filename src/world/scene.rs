@@ -0,0 +1,241 @@
+use crate::geometry::{Mesh, MeshSource};
+use crate::material::{Shader, ShaderKind, ShaderLit, ShaderUnlit};
+use crate::world::node::{Variant, LAYER_DEFAULT};
+use crate::world::{Node, NodeRef, Renderer, new_entity, new_group, new_light};
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use wgpu::Color;
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_layers() -> u32 {
+    LAYER_DEFAULT
+}
+
+fn default_scale() -> Vec3 {
+    Vec3::ONE
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SceneVariant {
+    Group,
+    Light { color: [f32; 4], radius: f32 },
+    Entity { mesh: MeshSource, shader: ShaderKind },
+}
+
+/// A node and everything below it, in a form that can be written to and
+/// read from RON or JSON. Fields left out of a file take their usual defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneNode {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extras: BTreeMap<String, f32>,
+    #[serde(default = "default_true")]
+    pub visible: bool,
+    #[serde(default = "default_layers")]
+    pub layers: u32,
+    #[serde(default)]
+    pub translation: Vec3,
+    #[serde(default)]
+    pub rotation: Quat,
+    #[serde(default = "default_scale")]
+    pub scale: Vec3,
+    pub variant: SceneVariant,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<SceneNode>,
+}
+
+/// Meshes and shaders made while building a scene, shared by every node
+/// asking for the same one
+struct Resources<'a> {
+    renderer: &'a Renderer,
+    meshes: Vec<(MeshSource, Rc<Mesh>)>,
+    shaders: Vec<(ShaderKind, Rc<dyn Shader>)>,
+}
+
+impl Resources<'_> {
+    fn mesh(&mut self, source: &MeshSource) -> Result<Rc<Mesh>, String> {
+        if let Some((_, mesh)) = self.meshes.iter().find(|(s, _)| s == source) {
+            return Ok(mesh.clone());
+        }
+        let mesh = source
+            .build(&self.renderer.device)
+            .ok_or_else(|| format!("can't rebuild mesh {source:?}"))?;
+        let mesh = Rc::new(mesh);
+        self.meshes.push((source.clone(), mesh.clone()));
+        Ok(mesh)
+    }
+    fn shader(&mut self, kind: ShaderKind) -> Rc<dyn Shader> {
+        if let Some((_, shader)) = self.shaders.iter().find(|(k, _)| *k == kind) {
+            return shader.clone();
+        }
+        let shader: Rc<dyn Shader> = match kind {
+            ShaderKind::Lit => Rc::new(ShaderLit::new(self.renderer)),
+            ShaderKind::Unlit => Rc::new(ShaderUnlit::new(self.renderer)),
+        };
+        self.shaders.push((kind, shader.clone()));
+        shader
+    }
+}
+
+impl SceneNode {
+    /// Snapshot `node` and its subtree
+    pub fn from_node(node: &NodeRef) -> Self {
+        let data = node.borrow();
+        let variant = match &data.variant {
+            Variant::Group => SceneVariant::Group,
+            Variant::Light(color, radius) => SceneVariant::Light {
+                color: [
+                    color.r as f32,
+                    color.g as f32,
+                    color.b as f32,
+                    color.a as f32,
+                ],
+                radius: *radius,
+            },
+            Variant::Entity(mesh, shader) => SceneVariant::Entity {
+                mesh: mesh.source.clone(),
+                shader: shader.kind(),
+            },
+        };
+        Self {
+            name: data.name.clone(),
+            tags: data.tags.clone(),
+            extras: data.extras.clone(),
+            visible: data.visible,
+            layers: data.layers,
+            translation: data.translation,
+            rotation: data.rotation,
+            scale: data.scale,
+            variant,
+            children: data.children.iter().map(SceneNode::from_node).collect(),
+        }
+    }
+    /// Make the nodes described here, with their meshes and shaders
+    pub fn build(&self, renderer: &Renderer) -> Result<NodeRef, String> {
+        let mut resources = Resources {
+            renderer,
+            meshes: Vec::new(),
+            shaders: Vec::new(),
+        };
+        self.build_with(&mut resources)
+    }
+    fn build_with(&self, resources: &mut Resources) -> Result<NodeRef, String> {
+        let mut node = match &self.variant {
+            SceneVariant::Group => new_group(),
+            SceneVariant::Light { color, radius } => {
+                let [r, g, b, a] = color.map(|c| c as f64);
+                new_light(Color { r, g, b, a }, *radius)
+            }
+            SceneVariant::Entity { mesh, shader } => {
+                new_entity(resources.mesh(mesh)?, resources.shader(*shader))
+            }
+        };
+        if let Some(name) = &self.name {
+            node.set_name(name);
+        }
+        for tag in self.tags.iter() {
+            node.add_tag(tag);
+        }
+        node.borrow_mut().extras = self.extras.clone();
+        node.set_visible(self.visible);
+        node.set_layers(self.layers);
+        node.translate(self.translation.x, self.translation.y, self.translation.z);
+        node.rotate_quat(self.rotation);
+        node.scale(self.scale.x, self.scale.y, self.scale.z);
+        for child in self.children.iter() {
+            node.add_child(child.build_with(resources)?);
+        }
+        Ok(node)
+    }
+    pub fn to_ron(&self) -> Result<String, String> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())
+    }
+    pub fn from_ron(text: &str) -> Result<Self, String> {
+        ron::from_str(text).map_err(|e| e.to_string())
+    }
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene() -> SceneNode {
+        let marker = SceneNode {
+            name: Some("marker".into()),
+            tags: vec!["light marker".into()],
+            extras: BTreeMap::new(),
+            visible: false,
+            layers: 1 << 8,
+            translation: Vec3::new(1.0, -2.0, 0.5),
+            rotation: Quat::from_rotation_y(0.3),
+            scale: Vec3::splat(0.7),
+            variant: SceneVariant::Entity {
+                mesh: MeshSource::Cube { color: 0xcba6f7ff },
+                shader: ShaderKind::Unlit,
+            },
+            children: Vec::new(),
+        };
+        SceneNode {
+            name: None,
+            tags: Vec::new(),
+            extras: BTreeMap::from([("time_offset".into(), 2200.0)]),
+            visible: true,
+            layers: LAYER_DEFAULT,
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+            variant: SceneVariant::Light {
+                color: [0.0, 0.5, 1.0, 1.0],
+                radius: 25.0,
+            },
+            children: vec![marker],
+        }
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let scene = scene();
+        let text = scene.to_ron().unwrap();
+        assert_eq!(SceneNode::from_ron(&text).unwrap(), scene);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let scene = scene();
+        let text = scene.to_json().unwrap();
+        assert_eq!(SceneNode::from_json(&text).unwrap(), scene);
+    }
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let node = SceneNode::from_ron("(variant: Group)").unwrap();
+        assert!(node.visible);
+        assert_eq!(node.layers, LAYER_DEFAULT);
+        assert_eq!(node.scale, Vec3::ONE);
+        assert_eq!(node.rotation, Quat::IDENTITY);
+        assert!(node.children.is_empty());
+    }
+
+    #[test]
+    fn bundled_scene_loads() {
+        let scene = SceneNode::from_ron(include_str!("../../assets/scene.ron")).unwrap();
+        assert_eq!(scene.children.len(), 4);
+        let text = scene.to_ron().unwrap();
+        assert_eq!(SceneNode::from_ron(&text).unwrap(), scene);
+    }
+}
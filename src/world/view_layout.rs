@@ -0,0 +1,111 @@
+use crate::world::{Camera, FULL_VIEW, Projection, Renderer, Viewport};
+use std::f32::consts::PI;
+
+/// How the window is split between cameras
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ViewLayout {
+    Single,
+    /// A small view from behind in the corner
+    Behind,
+    /// The main view next to orthographic front, top and side views
+    Grid,
+}
+
+impl ViewLayout {
+    pub const ALL: [ViewLayout; 3] = [ViewLayout::Single, ViewLayout::Behind, ViewLayout::Grid];
+    pub fn name(&self) -> &'static str {
+        match self {
+            ViewLayout::Single => "Single",
+            ViewLayout::Behind => "Main and behind",
+            ViewLayout::Grid => "2x2 grid",
+        }
+    }
+    fn main_rect(&self) -> [f32; 4] {
+        match self {
+            ViewLayout::Single | ViewLayout::Behind => FULL_VIEW,
+            ViewLayout::Grid => [0.0, 0.0, 0.5, 0.5],
+        }
+    }
+    /// Rect, azimuth and elevation of the other views, the ones from behind
+    /// taken relative to the main camera
+    fn views(&self) -> Vec<([f32; 4], f32, f32, Projection)> {
+        match self {
+            ViewLayout::Single => Vec::new(),
+            ViewLayout::Behind => vec![([0.7, 0.7, 0.28, 0.28], PI, 0.0, Projection::Perspective)],
+            ViewLayout::Grid => vec![
+                ([0.5, 0.0, 0.5, 0.5], 0.0, 0.0, Projection::Orthographic),
+                ([0.0, 0.5, 0.5, 0.5], 0.0, 1.5, Projection::Orthographic),
+                (
+                    [0.5, 0.5, 0.5, 0.5],
+                    PI * 0.5,
+                    0.0,
+                    Projection::Orthographic,
+                ),
+            ],
+        }
+    }
+    /// Point the other cameras at what the main one is looking at. Viewports
+    /// showing a scene of their own are left alone.
+    pub fn update(&self, renderer: &mut Renderer) {
+        renderer.viewport = self.main_rect();
+        let views = self.views();
+        let count = renderer
+            .viewports
+            .iter()
+            .filter(|v| v.scene.is_none())
+            .count();
+        if count != views.len() {
+            renderer
+                .viewports
+                .retain(|viewport| viewport.scene.is_some());
+            let cameras = views.iter().map(|(rect, ..)| Viewport {
+                camera: Camera::new(),
+                rect: *rect,
+                scene: None,
+            });
+            renderer.viewports.splice(0..0, cameras);
+        }
+        let main = &renderer.camera;
+        let cameras = renderer
+            .viewports
+            .iter_mut()
+            .filter(|viewport| viewport.scene.is_none());
+        for (viewport, (_, azimuth, elevation, projection)) in cameras.zip(views) {
+            let camera = &mut viewport.camera;
+            camera.target = main.target;
+            camera.layers = main.layers;
+            camera.projection = projection;
+            match self {
+                ViewLayout::Behind => camera.set_orbit(
+                    main.distance,
+                    main.azimuth + azimuth,
+                    main.elevation + elevation,
+                ),
+                _ => camera.set_orbit(main.framing_distance(), azimuth, elevation),
+            }
+        }
+    }
+}
+
+/// Pick how the window is split, framing the cube of `radius` again in the
+/// main view after a change
+pub fn layout_panel(
+    ui: &mut egui::Ui,
+    layout: &mut ViewLayout,
+    renderer: &mut Renderer,
+    radius: f32,
+) {
+    let mut picked = *layout;
+    egui::ComboBox::from_label("Views")
+        .selected_text(picked.name())
+        .show_ui(ui, |ui| {
+            for option in ViewLayout::ALL {
+                ui.selectable_value(&mut picked, option, option.name());
+            }
+        });
+    if picked != *layout {
+        *layout = picked;
+        picked.update(renderer);
+        renderer.camera.frame(radius, renderer.aspect_ratio());
+    }
+}